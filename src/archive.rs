@@ -0,0 +1,523 @@
+//! Unpacking archives into the temporary directory, and packing it
+//! back up, behind the `tar` and `zip` features.
+#[cfg(feature = "zip")]
+use crate::create_parent;
+#[cfg(feature = "zip")]
+use crate::tree::{self, Entry};
+use crate::{Operation, Result, TestFiles, TestFilesError};
+#[cfg(feature = "zip")]
+use std::fs;
+use std::io::Read;
+#[cfg(feature = "zip")]
+use std::io::{self, Cursor, Seek, Write};
+#[cfg(feature = "zip")]
+use std::path::Path;
+#[cfg(feature = "tar")]
+use std::path::PathBuf;
+
+/// Wraps a zip error as an [`io::Error`].
+#[cfg(feature = "zip")]
+fn zip_error(error: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Returns the permission bits of `path`, to be recorded in a zip
+/// archive.
+#[cfg(all(feature = "zip", unix))]
+fn mode(path: &Path) -> io::Result<Option<u32>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Ok(Some(fs::symlink_metadata(path)?.permissions().mode()))
+}
+
+#[cfg(all(feature = "zip", not(unix)))]
+fn mode(_path: &Path) -> io::Result<Option<u32>> {
+    Ok(None)
+}
+
+/// Applies permission bits recorded in a zip archive to `path`.
+#[cfg(all(feature = "zip", unix))]
+fn set_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "zip", not(unix)))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+impl TestFiles {
+    /// Returns the contents of `subdir`, relative to the temporary
+    /// directory, as a zip archive.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("bundle/plugin.toml", "name = \"demo\"");
+    ///
+    /// let archive = temp_dir.pack_zip("bundle");
+    /// let unpacked = test_files::TestFiles::new();
+    /// unpacked.unpack_zip(std::io::Cursor::new(archive));
+    ///
+    /// assert_eq!(unpacked.read("plugin.toml"), "name = \"demo\"");
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn pack_zip(&self, subdir: impl AsRef<Path>) -> Vec<u8> {
+        self.try_pack_zip(subdir).unwrap()
+    }
+
+    /// Tries to return the contents of `subdir`, relative to the
+    /// temporary directory, as a zip archive.  Entries are named
+    /// relative to `subdir` and use `/` separators; symlinks are
+    /// stored as symlinks and every directory is stored along with
+    /// its mode.
+    /// Timestamps are fixed, so the archive only depends on the
+    /// tree's contents and, on Unix, permissions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_dir("empty")?;
+    ///
+    /// let archive = temp_dir.try_pack_zip("")?;
+    /// assert_eq!(temp_dir.try_pack_zip("")?, archive);
+    ///
+    /// let unpacked = test_files::TestFiles::new();
+    /// unpacked.try_unpack_zip(std::io::Cursor::new(archive))?;
+    /// unpacked.assert_tree_eq("-- a.txt --\nok\n-- empty/ --\n");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn try_pack_zip(&self, subdir: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let subdir = subdir.as_ref();
+        let root = self.slash(subdir, Operation::Read)?;
+        let mut tree = tree::read(&root)?;
+        // The tree only lists empty directories, but every directory
+        // is stored to keep its mode.
+        let directories: Vec<_> = tree
+            .keys()
+            .flat_map(|path| path.ancestors().skip(1))
+            .filter(|path| *path != Path::new(""))
+            .map(|path| path.to_owned())
+            .collect();
+        for directory in directories {
+            tree.entry(directory).or_insert(Entry::Dir);
+        }
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, entry) in tree {
+            let name = tree::display(&path);
+            let relative = subdir.join(&path);
+            let absolute = root.join(&path);
+            let pack_error = || TestFilesError::io(Operation::Pack, &relative, &absolute);
+            let mut options = zip::write::SimpleFileOptions::default()
+                .last_modified_time(zip::DateTime::default());
+            if let Some(mode) = mode(&absolute).map_err(pack_error())? {
+                options = options.unix_permissions(mode);
+            }
+            match entry {
+                Entry::Dir => writer.add_directory(name, options),
+                Entry::File(content) => writer
+                    .start_file(name, options)
+                    .and_then(|()| Ok(writer.write_all(&content)?)),
+                Entry::Symlink(target) => {
+                    writer.add_symlink(name, target.to_string_lossy(), options)
+                }
+                Entry::Binary(_) | Entry::Text(_) => {
+                    unreachable!("trees read from disk hold file content")
+                }
+            }
+            .map_err(zip_error)
+            .map_err(pack_error())?;
+        }
+        let archive = writer
+            .finish()
+            .map_err(zip_error)
+            .map_err(TestFilesError::io(Operation::Pack, subdir, &root))?;
+        Ok(archive.into_inner())
+    }
+
+    /// Tries to extract every entry of a tar archive under the
+    /// temporary directory.  Entries whose paths would escape the
+    /// temporary directory, including through symlinks, are refused
+    /// with [`TestFilesError::PathEscapesRoot`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let mut builder = tar::Builder::new(Vec::new());
+    /// let mut header = tar::Header::new_gnu();
+    /// header.set_size(2);
+    /// builder.append_data(&mut header, "a/b.txt", &b"ok"[..]).unwrap();
+    /// let archive = builder.into_inner().unwrap();
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_unpack_tar(&archive[..])?;
+    ///
+    /// assert_eq!(temp_dir.try_read("a/b.txt")?, "ok");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tar")]
+    pub fn try_unpack_tar(&self, reader: impl Read) -> Result<&Self> {
+        let archive_error = || TestFilesError::io(Operation::Unpack, ".", self.path());
+        let mut archive = tar::Archive::new(reader);
+        let mut directories = Vec::new();
+        for entry in archive.entries().map_err(archive_error())? {
+            let entry = entry.map_err(archive_error())?;
+            let path = entry.path().map_err(archive_error())?.into_owned();
+            let absolute = self.slash(&path, Operation::Unpack)?;
+            if entry.header().entry_type().is_dir() {
+                directories.push((path, absolute, entry));
+            } else {
+                self.unpack_tar_entry(path, absolute, entry)?;
+            }
+        }
+        // Like `tar::Archive::unpack`, directories come last and
+        // deepest first, so that their modes cannot forbid writing
+        // their entries.
+        directories.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+        for (path, absolute, entry) in directories {
+            self.unpack_tar_entry(path, absolute, entry)?;
+        }
+        Ok(self)
+    }
+
+    /// Tries to extract every entry of a gzip-compressed tar archive
+    /// under the temporary directory, see
+    /// [`TestFiles::try_unpack_tar`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flate2::write::GzEncoder;
+    /// use flate2::Compression;
+    /// use test_files::{Operation, TestFilesError};
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let mut header = tar::Header::new_gnu();
+    /// // `Header::set_path` refuses `..`, so the name is set directly.
+    /// header.as_old_mut().name[..14].copy_from_slice(b"../escaped.txt");
+    /// header.set_size(2);
+    /// header.set_cksum();
+    /// let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    /// builder.append(&header, &b"no"[..]).unwrap();
+    /// let archive = builder.into_inner().unwrap().finish().unwrap();
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let error = temp_dir.try_unpack_tar_gz(&archive[..]).unwrap_err();
+    ///
+    /// assert!(matches!(error, TestFilesError::PathEscapesRoot { .. }));
+    /// assert_eq!(error.operation(), Operation::Unpack);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tar")]
+    pub fn try_unpack_tar_gz(&self, reader: impl Read) -> Result<&Self> {
+        self.try_unpack_tar(flate2::read::GzDecoder::new(reader))
+    }
+
+    /// Tries to extract every entry of a zip archive under the
+    /// temporary directory.  Entries whose paths would escape the
+    /// temporary directory are refused with
+    /// [`TestFilesError::PathEscapesRoot`]; symlinks are recreated
+    /// as symlinks and, on Unix, recorded permissions are applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::{Cursor, Write};
+    /// use zip::write::SimpleFileOptions;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    /// writer.start_file("plugin/main.lua", SimpleFileOptions::default()).unwrap();
+    /// writer.write_all(b"print('hi')").unwrap();
+    /// let archive = writer.finish().unwrap();
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_unpack_zip(archive)?;
+    ///
+    /// assert_eq!(temp_dir.try_read("plugin/main.lua")?, "print('hi')");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn try_unpack_zip(&self, reader: impl Read + Seek) -> Result<&Self> {
+        let archive_error = || TestFilesError::io(Operation::Unpack, ".", self.path());
+        let mut archive = zip::ZipArchive::new(reader)
+            .map_err(zip_error)
+            .map_err(archive_error())?;
+        let mut directories = Vec::new();
+        for index in 0..archive.len() {
+            let mut file = archive
+                .by_index(index)
+                .map_err(zip_error)
+                .map_err(archive_error())?;
+            let path = file
+                .enclosed_name()
+                .ok_or_else(|| TestFilesError::PathEscapesRoot {
+                    operation: Operation::Unpack,
+                    path: file.name().into(),
+                    absolute: self.path().join(file.name()),
+                })?;
+            let absolute = self.slash(&path, Operation::Unpack)?;
+            let unpack_error = || TestFilesError::io(Operation::Unpack, &path, &absolute);
+            if file.is_dir() {
+                fs::create_dir_all(&absolute).map_err(unpack_error())?;
+                directories.push((path, absolute, file.unix_mode()));
+                continue;
+            }
+            create_parent(&path, &absolute)?;
+            if file.is_symlink() {
+                let mut target = String::new();
+                file.read_to_string(&mut target).map_err(unpack_error())?;
+                tree::symlink(Path::new(&target), &absolute).map_err(unpack_error())?;
+                continue;
+            }
+            let mut unpacked = fs::File::create(&absolute).map_err(unpack_error())?;
+            io::copy(&mut file, &mut unpacked).map_err(unpack_error())?;
+            set_mode(&absolute, file.unix_mode()).map_err(unpack_error())?;
+        }
+        // Directory modes are applied last and deepest first, so that
+        // they cannot forbid writing their entries.
+        directories.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+        for (path, absolute, mode) in directories {
+            set_mode(&absolute, mode).map_err(TestFilesError::io(
+                Operation::Unpack,
+                &path,
+                &absolute,
+            ))?;
+        }
+        Ok(self)
+    }
+
+    /// Extracts every entry of a tar archive under the temporary
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let source = test_files::TestFiles::new();
+    /// source.file("src/main.rs", "fn main() {}");
+    /// let mut builder = tar::Builder::new(Vec::new());
+    /// builder.append_dir_all("project", source.path()).unwrap();
+    /// let archive = builder.into_inner().unwrap();
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.unpack_tar(&archive[..]);
+    ///
+    /// assert_eq!(temp_dir.read("project/src/main.rs"), "fn main() {}");
+    /// ```
+    #[cfg(feature = "tar")]
+    pub fn unpack_tar(&self, reader: impl Read) -> &Self {
+        self.try_unpack_tar(reader).unwrap()
+    }
+
+    /// Extracts a single tar `entry`, already checked to resolve to
+    /// `absolute`, under the temporary directory.
+    #[cfg(feature = "tar")]
+    fn unpack_tar_entry<R: Read>(
+        &self,
+        path: PathBuf,
+        absolute: PathBuf,
+        mut entry: tar::Entry<'_, R>,
+    ) -> Result<()> {
+        let unpacked = entry.unpack_in(self.path()).map_err(TestFilesError::io(
+            Operation::Unpack,
+            &path,
+            &absolute,
+        ))?;
+        if !unpacked {
+            return Err(TestFilesError::PathEscapesRoot {
+                operation: Operation::Unpack,
+                path,
+                absolute,
+            });
+        }
+        Ok(())
+    }
+
+    /// Extracts every entry of a gzip-compressed tar archive under
+    /// the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use flate2::write::GzEncoder;
+    /// use flate2::Compression;
+    ///
+    /// let mut header = tar::Header::new_gnu();
+    /// header.set_size(4);
+    /// let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    /// builder.append_data(&mut header, "fine.txt", &b"fine"[..]).unwrap();
+    /// let archive = builder.into_inner().unwrap().finish().unwrap();
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.unpack_tar_gz(&archive[..]);
+    ///
+    /// assert_eq!(temp_dir.read("fine.txt"), "fine");
+    /// ```
+    #[cfg(feature = "tar")]
+    pub fn unpack_tar_gz(&self, reader: impl Read) -> &Self {
+        self.try_unpack_tar_gz(reader).unwrap()
+    }
+
+    /// Extracts every entry of a zip archive under the temporary
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    ///
+    /// let bundle = test_files::TestFiles::new();
+    /// bundle.file("plugin.toml", "name = \"demo\"");
+    /// bundle.file("demo.zip", bundle.pack_zip(""));
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.unpack_zip(File::open(bundle.path().join("demo.zip")).unwrap());
+    ///
+    /// assert_eq!(temp_dir.read("plugin.toml"), "name = \"demo\"");
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn unpack_zip(&self, reader: impl Read + Seek) -> &Self {
+        self.try_unpack_zip(reader).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::Result;
+    #[cfg(unix)]
+    use std::fs;
+
+    #[cfg(all(unix, feature = "tar"))]
+    #[test]
+    fn refuses_tar_entries_through_escaping_symlinks() -> Result<()> {
+        let outside = TestFiles::new();
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut link = tar::Header::new_gnu();
+        link.set_entry_type(tar::EntryType::Symlink);
+        link.set_size(0);
+        builder.append_link(&mut link, "escape", outside.path())?;
+        let mut file = tar::Header::new_gnu();
+        file.set_size(2);
+        builder.append_data(&mut file, "escape/a.txt", &b"no"[..])?;
+        let archive = builder.into_inner()?;
+
+        let files = TestFiles::new();
+        assert!(matches!(
+            files.try_unpack_tar(&archive[..]),
+            Err(TestFilesError::PathEscapesRoot { .. })
+        ));
+        assert!(!outside.path().join("a.txt").exists());
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "tar"))]
+    #[test]
+    fn unpacks_tar_entries_into_read_only_directories() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut dir = tar::Header::new_gnu();
+        dir.set_entry_type(tar::EntryType::Directory);
+        dir.set_mode(0o555);
+        dir.set_size(0);
+        builder.append_data(&mut dir, "ro/", std::io::empty())?;
+        let mut file = tar::Header::new_gnu();
+        file.set_mode(0o644);
+        file.set_size(2);
+        builder.append_data(&mut file, "ro/a.txt", &b"ok"[..])?;
+        let archive = builder.into_inner()?;
+
+        let files = TestFiles::new();
+        files.unpack_tar(&archive[..]);
+
+        let mode = fs::metadata(files.path().join("ro"))?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o555);
+        assert_eq!(files.read("ro/a.txt"), "ok");
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "zip"))]
+    #[test]
+    fn round_trips_zip_archives() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let files = TestFiles::new();
+        files
+            .file("bundle/run.sh", "#!/bin/sh")
+            .chmod("bundle/run.sh", 0o755)
+            .symlink("bundle/run", "run.sh")
+            .dir("bundle/empty");
+
+        let unpacked = TestFiles::new();
+        unpacked.unpack_zip(std::io::Cursor::new(files.pack_zip("bundle")));
+
+        let mode = fs::metadata(unpacked.path().join("run.sh"))?
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(unpacked.read("run.sh"), "#!/bin/sh");
+        assert_eq!(
+            fs::read_link(unpacked.path().join("run"))?,
+            Path::new("run.sh")
+        );
+        assert!(unpacked.path().join("empty").is_dir());
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn refuses_zip_entries_escaping_root() -> Result<()> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("../escape.txt", zip::write::SimpleFileOptions::default())?;
+        let archive = writer.finish()?;
+
+        let files = TestFiles::new();
+        assert!(matches!(
+            files.try_unpack_zip(archive),
+            Err(TestFilesError::PathEscapesRoot { .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "zip"))]
+    #[test]
+    fn round_trips_zip_archives_with_read_only_directories() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let files = TestFiles::new();
+        files.file("b/ro/a.txt", "ok").chmod("b/ro", 0o555);
+
+        let unpacked = TestFiles::new();
+        unpacked.unpack_zip(std::io::Cursor::new(files.pack_zip("")));
+
+        let mode = fs::metadata(unpacked.path().join("b/ro"))?
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o555);
+        assert_eq!(unpacked.read("b/ro/a.txt"), "ok");
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+//! Configuration of the temporary directory backing a [`TestFiles`].
+use crate::{Keep, Operation, Result, TestFiles, TestFilesError};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, Permissions};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+/// Builds a [`TestFiles`] with a customised temporary directory,
+/// see [`TestFiles::builder`].
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    parent: Option<PathBuf>,
+    prefix: Option<OsString>,
+    suffix: Option<OsString>,
+    permissions: Option<Permissions>,
+    keep: Option<Keep>,
+    epoch: Option<SystemTime>,
+    root_token: Option<String>,
+}
+
+impl Builder {
+    /// Creates the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::builder().build();
+    ///
+    /// assert!(temp_dir.path().is_dir());
+    /// ```
+    pub fn build(&self) -> TestFiles {
+        self.try_build().unwrap()
+    }
+
+    /// Stamps every file written through [`TestFiles::try_file`] with
+    /// `epoch` as both its access and modification time, so that
+    /// timestamps do not depend on when a test runs.
+    ///
+    /// Only such files are covered: directories, links, copied
+    /// fixtures and unpacked archives keep their real timestamps.
+    /// Call [`TestFiles::stamp`] once the fixture is complete to
+    /// stamp every entry instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::UNIX_EPOCH;
+    ///
+    /// let temp_dir = test_files::TestFiles::builder().epoch(UNIX_EPOCH).build();
+    /// temp_dir.file("a.txt", "ok");
+    ///
+    /// let modified = fs::metadata(temp_dir.path().join("a.txt")).unwrap().modified().unwrap();
+    /// assert_eq!(modified, UNIX_EPOCH);
+    /// ```
+    pub fn epoch(mut self, epoch: SystemTime) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Creates the temporary directory under `parent` rather than
+    /// the system default, creating `parent` first if necessary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let parent = test_files::TestFiles::new();
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .in_dir(parent.path().join("target").join("tmp"))
+    ///     .build();
+    ///
+    /// assert!(temp_dir.path().starts_with(parent.path()));
+    /// ```
+    pub fn in_dir(mut self, parent: impl AsRef<Path>) -> Self {
+        self.parent = Some(parent.as_ref().to_owned());
+        self
+    }
+
+    /// Sets when the temporary directory is kept rather than removed,
+    /// overriding the `TEST_FILES_KEEP` environment variable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use test_files::Keep;
+    ///
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .keep(Keep::OnFailure)
+    ///     .build();
+    ///
+    /// assert!(temp_dir.path().is_dir());
+    /// ```
+    pub fn keep(mut self, keep: Keep) -> Self {
+        self.keep = Some(keep);
+        self
+    }
+
+    /// Sets the permissions of the temporary directory itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::fs::{self, Permissions};
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .permissions(Permissions::from_mode(0o750))
+    ///     .build();
+    ///
+    /// let mode = fs::metadata(temp_dir.path()).unwrap().permissions().mode();
+    /// assert_eq!(mode & 0o777, 0o750);
+    /// # }
+    /// ```
+    pub fn permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Sets the start of the temporary directory name, for instance
+    /// to include the name of the test using it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .prefix("my-test-")
+    ///     .build();
+    ///
+    /// let name = temp_dir.path().file_name().unwrap().to_str().unwrap();
+    /// assert!(name.starts_with("my-test-"));
+    /// ```
+    pub fn prefix(mut self, prefix: impl AsRef<OsStr>) -> Self {
+        self.prefix = Some(prefix.as_ref().to_owned());
+        self
+    }
+
+    /// Stands `token` in for the path of the temporary directory:
+    /// text files loaded from a txtar archive or a fixture directory
+    /// have `token` replaced with the path, and text files exported
+    /// to txtar, diffed or snapshotted have the path replaced with
+    /// `token`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::builder().root_token("{root}").build();
+    /// temp_dir.txtar("-- config.toml --\ncache = \"{root}/cache\"\n");
+    ///
+    /// let expected = format!("cache = \"{}/cache\"\n", temp_dir.path().display());
+    /// assert_eq!(temp_dir.read("config.toml"), expected);
+    /// assert_eq!(
+    ///     temp_dir.to_txtar(),
+    ///     "-- config.toml --\ncache = \"{root}/cache\"\n"
+    /// );
+    /// ```
+    pub fn root_token(mut self, token: impl Into<String>) -> Self {
+        self.root_token = Some(token.into());
+        self
+    }
+
+    /// Sets the end of the temporary directory name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .suffix(".fixture")
+    ///     .build();
+    ///
+    /// let name = temp_dir.path().file_name().unwrap().to_str().unwrap();
+    /// assert!(name.ends_with(".fixture"));
+    /// ```
+    pub fn suffix(mut self, suffix: impl AsRef<OsStr>) -> Self {
+        self.suffix = Some(suffix.as_ref().to_owned());
+        self
+    }
+
+    /// Tries to create the temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::builder().prefix("my-test-").try_build();
+    ///
+    /// assert!(temp_dir.is_ok());
+    /// assert!(temp_dir.unwrap().path().is_dir());
+    /// ```
+    pub fn try_build(&self) -> Result<TestFiles> {
+        let mut builder = tempfile::Builder::new();
+        if let Some(prefix) = &self.prefix {
+            builder.prefix(prefix);
+        }
+        if let Some(suffix) = &self.suffix {
+            builder.suffix(suffix);
+        }
+        if let Some(permissions) = &self.permissions {
+            builder.permissions(permissions.clone());
+        }
+        let parent = self.parent.clone().unwrap_or_else(env::temp_dir);
+        let absolute = std::path::absolute(&parent).unwrap_or_else(|_| parent.clone());
+        if self.parent.is_some() {
+            fs::create_dir_all(&parent).map_err(TestFilesError::io(
+                Operation::CreateDir,
+                &parent,
+                &absolute,
+            ))?;
+        }
+        let dir = builder.tempdir_in(&parent).map_err(TestFilesError::io(
+            Operation::CreateTempDir,
+            &parent,
+            &absolute,
+        ))?;
+        Ok(TestFiles {
+            dir,
+            keep: self.keep.unwrap_or_else(Keep::from_env),
+            epoch: self.epoch,
+            root_token: self.root_token.clone(),
+            hermetic_home: OnceLock::new(),
+        })
+    }
+}
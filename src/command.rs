@@ -0,0 +1,283 @@
+//! Running programs inside the fixture.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use std::borrow::Cow;
+use std::env;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Output};
+
+/// The exit status and captured output of a program run through
+/// [`TestFiles::run`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// How the program exited.
+    pub status: ExitStatus,
+    /// Everything the program wrote to standard output.
+    pub stdout: Vec<u8>,
+    /// Everything the program wrote to standard error.
+    pub stderr: Vec<u8>,
+}
+
+impl From<Output> for CommandOutput {
+    fn from(output: Output) -> Self {
+        Self {
+            status: output.status,
+            stdout: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+impl CommandOutput {
+    /// Asserts that the program failed.
+    ///
+    /// Panics with the captured output otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    ///
+    /// temp_dir.run(&mut temp_dir.command("false")).assert_failure();
+    /// # }
+    /// ```
+    pub fn assert_failure(&self) -> &Self {
+        assert!(
+            !self.status.success(),
+            "command unexpectedly succeeded\n{}",
+            self.describe()
+        );
+        self
+    }
+
+    /// Asserts that the program succeeded.
+    ///
+    /// Panics with the captured output otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.command("sh");
+    /// command.args(["-c", "echo done"]);
+    ///
+    /// let output = temp_dir.run(&mut command);
+    /// assert_eq!(output.assert_success().stdout_lossy(), "done\n");
+    /// # }
+    /// ```
+    pub fn assert_success(&self) -> &Self {
+        assert!(self.status.success(), "command failed\n{}", self.describe());
+        self
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "status: {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+            self.status,
+            self.stdout_lossy(),
+            self.stderr_lossy()
+        )
+    }
+
+    /// Returns standard error decoded as UTF-8, replacing invalid
+    /// sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.command("sh");
+    /// command.args(["-c", "echo oops >&2"]);
+    ///
+    /// assert_eq!(temp_dir.run(&mut command).stderr_lossy(), "oops\n");
+    /// # }
+    /// ```
+    pub fn stderr_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+
+    /// Returns standard output decoded as UTF-8, replacing invalid
+    /// sequences.  The raw bytes are in [`CommandOutput::stdout`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.command("printf");
+    /// command.arg("ok\\377");
+    ///
+    /// let output = temp_dir.run(&mut command);
+    /// assert_eq!(output.stdout, b"ok\xff");
+    /// assert_eq!(output.stdout_lossy(), "ok\u{fffd}");
+    /// # }
+    /// ```
+    pub fn stdout_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+}
+
+impl TestFiles {
+    /// Returns a [`Command`] for `program` which runs in the
+    /// temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.txt", "ok");
+    ///
+    /// let output = temp_dir.command("cat").arg("a.txt").output().unwrap();
+    /// assert_eq!(output.stdout, b"ok");
+    /// # }
+    /// ```
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        command.current_dir(self.path());
+        command
+    }
+
+    /// Returns a [`Command`] for `program` which runs in the temporary
+    /// directory with a hermetic environment, see
+    /// [`TestFiles::try_hermetic_command`].
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::path::Path;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.hermetic_command("sh");
+    /// command.args(["-c", "echo $HOME; echo ok > out.txt"]);
+    ///
+    /// let output = temp_dir.run(&mut command);
+    /// assert!(!Path::new(output.stdout_lossy().trim_end()).starts_with(temp_dir.path()));
+    /// temp_dir.assert_tree_eq("-- out.txt --\nok\n");
+    /// # }
+    /// ```
+    pub fn hermetic_command(&self, program: impl AsRef<OsStr>) -> Command {
+        self.try_hermetic_command(program).unwrap()
+    }
+
+    /// Runs `command` to completion, capturing its output.
+    ///
+    /// Panics on failure to run the program, or if it does not run in
+    /// the temporary directory, but not on the program failing: see
+    /// [`CommandOutput::assert_success`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.command("sh");
+    /// command.args(["-c", "echo ok > a.txt"]);
+    ///
+    /// temp_dir.run(&mut command).assert_success();
+    /// temp_dir.assert_tree_eq("-- a.txt --\nok\n");
+    /// # }
+    /// ```
+    pub fn run(&self, command: &mut Command) -> CommandOutput {
+        self.try_run(command).unwrap()
+    }
+
+    /// Tries to return a [`Command`] for `program` which runs in the
+    /// temporary directory with a hermetic environment: only `PATH` is
+    /// inherited, and `HOME` and the XDG base directories point into
+    /// an empty home directory.  That home is kept beside the temporary
+    /// directory, so it neither shows up in the tree under test nor in
+    /// the program's working directory.  To run with a home populated
+    /// through [`TestFiles::home`] instead, add its
+    /// [`envs`](crate::Home::envs) to the command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.try_home()?;
+    /// home.try_file(".config/my-tool/config.toml", "verbose = true")?;
+    ///
+    /// let mut command = temp_dir.try_hermetic_command("my-tool")?;
+    /// command.envs(home.envs());
+    ///
+    /// let envs: Vec<_> = command.get_envs().collect();
+    /// assert!(envs.contains(&("HOME".as_ref(), Some(home.path().as_os_str()))));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_hermetic_command(&self, program: impl AsRef<OsStr>) -> Result<Command> {
+        let envs = self.try_hermetic_envs()?;
+        let mut command = self.command(program);
+        command.env_clear().envs(envs);
+        if let Some(path) = env::var_os("PATH") {
+            command.env("PATH", path);
+        }
+        Ok(command)
+    }
+
+    /// Tries to run `command` to completion, capturing its output.
+    /// Only failing to run the program is an error; check the
+    /// returned status for the program's own failure.
+    ///
+    /// `command` must run in the temporary directory or one below it,
+    /// as set up by [`TestFiles::command`], or this fails with
+    /// [`TestFilesError::PathEscapesRoot`] without running it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(unix)]
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut command = temp_dir.command("sh");
+    /// command.args(["-c", "echo oops >&2; exit 3"]);
+    ///
+    /// let output = temp_dir.try_run(&mut command)?;
+    /// assert_eq!(output.status.code(), Some(3));
+    /// assert_eq!(output.stderr, b"oops\n");
+    /// #   Ok(())
+    /// # }
+    /// # #[cfg(not(unix))]
+    /// # fn main() {}
+    /// ```
+    pub fn try_run(&self, command: &mut Command) -> Result<CommandOutput> {
+        // Without a directory of its own, the command runs in ours.
+        let dir = command.get_current_dir().unwrap_or(Path::new("."));
+        let root = self.path().canonicalize().map_err(TestFilesError::io(
+            Operation::Resolve,
+            ".",
+            self.path(),
+        ))?;
+        let absolute =
+            dir.canonicalize()
+                .map_err(TestFilesError::io(Operation::Spawn, dir, dir))?;
+        if !absolute.starts_with(&root) {
+            return Err(TestFilesError::PathEscapesRoot {
+                operation: Operation::Spawn,
+                path: dir.to_owned(),
+                absolute,
+            });
+        }
+        let output = command.output().map_err(TestFilesError::io(
+            Operation::Spawn,
+            command.get_program(),
+            absolute,
+        ))?;
+        Ok(output.into())
+    }
+}
@@ -0,0 +1,204 @@
+//! Materializing fixtures from directories checked in next to the
+//! tests.
+use crate::{tree, Operation, Result, TestFiles, TestFilesError};
+use std::fs;
+use std::path::Path;
+
+impl TestFiles {
+    /// Copies the directory `src` into the temporary directory at
+    /// `dest`, relative to the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let fixture = test_files::TestFiles::new();
+    /// fixture.file("Cargo.toml", "[package]");
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.copy_in(fixture.path(), "project-a");
+    ///
+    /// assert_eq!(temp_dir.read("project-a/Cargo.toml"), "[package]");
+    /// ```
+    pub fn copy_in(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> &Self {
+        self.try_copy_in(src, dest).unwrap()
+    }
+
+    /// Creates a new temporary directory holding a copy of the `src`
+    /// directory, e.g. `tests/fixtures/project-a`.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let fixture = test_files::TestFiles::new();
+    /// fixture.file("src/main.rs", "fn main() {}");
+    ///
+    /// let temp_dir = test_files::TestFiles::from_dir(fixture.path());
+    /// temp_dir.file("src/main.rs", "fn main() { todo!() }");
+    ///
+    /// assert_eq!(fixture.read("src/main.rs"), "fn main() {}");
+    /// ```
+    pub fn from_dir(src: impl AsRef<Path>) -> Self {
+        Self::try_from_dir(src).unwrap()
+    }
+
+    /// Tries to copy the directory `src` into the temporary directory
+    /// at `dest`, relative to the temporary directory, merging with
+    /// anything already there.  Symlinks are copied as symlinks and,
+    /// on Unix, permissions are preserved, except for the mode of `src`
+    /// itself, which leaves `dest` as writable as before.  Text files
+    /// have any
+    /// [`Builder::root_token`](crate::Builder::root_token) replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let fixture = test_files::TestFiles::new();
+    /// fixture.try_file("config/app.toml", "verbose = true")?.try_dir("empty")?;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_copy_in(fixture.path(), "")?;
+    ///
+    /// temp_dir.assert_tree_eq("-- config/app.toml --\nverbose = true\n-- empty/ --\n");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_copy_in(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<&Self> {
+        self.copy_dir(src.as_ref(), Path::new(""), dest.as_ref())?;
+        Ok(self)
+    }
+
+    /// Tries to create a new temporary directory holding a copy of
+    /// the `src` directory, see [`TestFiles::try_copy_in`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::try_from_dir("does/not/exist");
+    ///
+    /// assert!(temp_dir.is_err());
+    /// ```
+    pub fn try_from_dir(src: impl AsRef<Path>) -> Result<Self> {
+        let files = Self::try_new()?;
+        files.try_copy_in(src, "")?;
+        Ok(files)
+    }
+
+    /// Copies `relative` within the `src` directory to `relative`
+    /// within `dest`.
+    fn copy_dir(&self, src: &Path, relative: &Path, dest: &Path) -> Result<()> {
+        let src_dir = src.join(relative);
+        let dest_path = dest.join(relative);
+        let dest_dir = self.slash(&dest_path, Operation::Copy)?;
+        fs::create_dir_all(&dest_dir).map_err(TestFilesError::io(
+            Operation::CreateDir,
+            &dest_path,
+            &dest_dir,
+        ))?;
+
+        let read_dir_error = || TestFilesError::io(Operation::ReadDir, &src_dir, &src_dir);
+        for entry in fs::read_dir(&src_dir).map_err(read_dir_error())? {
+            let entry = entry.map_err(read_dir_error())?;
+            let relative = relative.join(entry.file_name());
+            let file_type = entry.file_type().map_err(read_dir_error())?;
+            if file_type.is_dir() {
+                self.copy_dir(src, &relative, dest)?;
+                continue;
+            }
+
+            let dest_path = dest.join(&relative);
+            let dest_entry = self.slash(&dest_path, Operation::Copy)?;
+            let copy_error = || TestFilesError::io(Operation::Copy, &dest_path, &dest_entry);
+            if file_type.is_symlink() {
+                let target = fs::read_link(entry.path()).map_err(TestFilesError::io(
+                    Operation::Read,
+                    entry.path(),
+                    entry.path(),
+                ))?;
+                tree::symlink(&target, &dest_entry).map_err(copy_error())?;
+            } else if self.root_token.is_some() {
+                let read_error = || TestFilesError::io(Operation::Read, entry.path(), entry.path());
+                let content = fs::read(entry.path()).map_err(read_error())?;
+                let permissions = entry.metadata().map_err(read_error())?.permissions();
+                fs::write(&dest_entry, self.expand_root(content)).map_err(copy_error())?;
+                fs::set_permissions(&dest_entry, permissions).map_err(copy_error())?;
+            } else {
+                fs::copy(entry.path(), &dest_entry).map_err(copy_error())?;
+            }
+        }
+
+        // `src` itself is only a container: its mode, say that of a
+        // read-only checkout, must not make `dest` unwritable.
+        if relative == Path::new("") {
+            return Ok(());
+        }
+        // Permissions are copied last, as they may forbid writing
+        // the directory's entries.
+        let permissions = fs::metadata(&src_dir)
+            .map_err(read_dir_error())?
+            .permissions();
+        fs::set_permissions(&dest_dir, permissions).map_err(TestFilesError::io(
+            Operation::Copy,
+            &dest_path,
+            &dest_dir,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::Result;
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_symlinks_and_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = TestFiles::new();
+        fixture
+            .file("bin/run.sh", "#!/bin/sh")
+            .chmod("bin/run.sh", 0o755)
+            .symlink("run", "bin/run.sh")
+            .chmod("bin", 0o555);
+
+        let files = TestFiles::from_dir(fixture.path());
+
+        let mode = |path: &str| -> Result<u32> {
+            Ok(fs::symlink_metadata(files.path().join(path))?
+                .permissions()
+                .mode()
+                & 0o777)
+        };
+        assert_eq!(mode("bin/run.sh")?, 0o755);
+        assert_eq!(mode("bin")?, 0o555);
+        assert_eq!(
+            fs::read_link(files.path().join("run"))?,
+            Path::new("bin/run.sh")
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_root_writable_when_copying_read_only_fixtures() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = TestFiles::new();
+        fixture.file("a.txt", "ok").chmod("", 0o555);
+
+        let files = TestFiles::from_dir(fixture.path());
+        files.file("b.txt", "new");
+
+        let mode = |files: &TestFiles| -> Result<u32> {
+            Ok(fs::metadata(files.path())?.permissions().mode())
+        };
+        assert_eq!(mode(&files)?, mode(&TestFiles::new())?);
+        assert_eq!(files.read("a.txt"), "ok");
+        Ok(())
+    }
+}
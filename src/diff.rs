@@ -0,0 +1,172 @@
+//! Comparing the tree on disk against an expected tree.
+use crate::tree::{self, Entry, Tree};
+use crate::{txtar, Result, TestFiles};
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Differences between the files under a directory and the files
+/// expected there.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Expected files and empty directories which do not exist.
+    pub missing: Vec<PathBuf>,
+    /// Existing files and empty directories which were not expected.
+    pub unexpected: Vec<PathBuf>,
+    /// Files whose content differs, each with a unified diff from the
+    /// expected to the actual content.
+    pub changed: Vec<(PathBuf, String)>,
+    directories: BTreeSet<PathBuf>,
+}
+
+impl TreeDiff {
+    pub(crate) fn new(expected: &Tree, actual: &Tree) -> Self {
+        let mut diff = Self::default();
+        for (path, expected_entry) in expected {
+            match actual.get(path) {
+                None => diff.missing.push(path.clone()),
+                Some(actual_entry) if !expected_entry.matches(actual_entry) => diff
+                    .changed
+                    .push((path.clone(), unified(path, expected_entry, actual_entry))),
+                Some(_) => {}
+            }
+        }
+        diff.unexpected = actual
+            .keys()
+            .filter(|path| !expected.contains_key(*path))
+            .cloned()
+            .collect();
+        diff.directories = expected
+            .iter()
+            .chain(actual)
+            .filter(|(_, entry)| **entry == Entry::Dir)
+            .map(|(path, _)| path.clone())
+            .collect();
+        diff
+    }
+
+    fn kind(&self, path: &Path) -> &'static str {
+        if self.directories.contains(path) {
+            "directory"
+        } else {
+            "file"
+        }
+    }
+
+    /// Returns `true` if the trees are identical.
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn unified(path: &Path, expected: &Entry, actual: &Entry) -> String {
+    let path = tree::display(path);
+    match (text(expected), text(actual)) {
+        (Some(expected), Some(actual)) => TextDiff::from_lines(expected, actual)
+            .unified_diff()
+            .header(&format!("expected/{}", path), &format!("actual/{}", path))
+            .to_string(),
+        _ => format!(
+            "{} differs: expected {}, found {}\n",
+            path,
+            expected.describe(),
+            actual.describe()
+        ),
+    }
+}
+
+fn text(entry: &Entry) -> Option<&str> {
+    match entry {
+        Entry::File(content) => std::str::from_utf8(content).ok(),
+        Entry::Dir | Entry::Binary(_) | Entry::Text(_) | Entry::Symlink(_) => None,
+    }
+}
+
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.missing {
+            writeln!(f, "missing {}: {}", self.kind(path), tree::display(path))?;
+        }
+        for path in &self.unexpected {
+            writeln!(f, "unexpected {}: {}", self.kind(path), tree::display(path))?;
+        }
+        for (_, diff) in &self.changed {
+            write!(f, "{}", diff)?;
+        }
+        Ok(())
+    }
+}
+
+impl TestFiles {
+    /// Asserts that the files under the temporary directory are
+    /// exactly those of the `expected` txtar archive.
+    ///
+    /// Panics with a description of every difference otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use indoc::indoc;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a/b/c.txt", "ok").file("b/c/d.txt", "fine");
+    ///
+    /// temp_dir.assert_tree_eq(indoc! {"
+    ///     -- a/b/c.txt --
+    ///     ok
+    ///     -- b/c/d.txt --
+    ///     fine
+    /// "});
+    /// ```
+    pub fn assert_tree_eq(&self, expected: &str) {
+        let diff = self.diff_tree(expected).unwrap();
+        assert!(diff.is_empty(), "file tree mismatch:\n{}", diff);
+    }
+
+    /// Compares the files under the temporary directory against the
+    /// `expected` txtar archive.  As in the archive, a final newline
+    /// is assumed on any non-empty text file missing one, empty
+    /// directories are listed with a trailing `/`, and the placeholders
+    /// written by [`TestFiles::to_txtar`] match symlinks and binary
+    /// files of the given length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use indoc::indoc;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok\n")?.try_file("b.txt", "unexpected")?;
+    ///
+    /// let diff = temp_dir.diff_tree(indoc! {"
+    ///     -- a.txt --
+    ///     fine
+    ///     -- c.txt --
+    ///     missing
+    /// "})?;
+    /// assert_eq!(diff.missing, vec![PathBuf::from("c.txt")]);
+    /// assert_eq!(diff.unexpected, vec![PathBuf::from("b.txt")]);
+    /// assert_eq!(diff.changed.len(), 1);
+    /// assert!(diff.to_string().contains("-fine\n+ok\n"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn diff_tree(&self, expected: &str) -> Result<TreeDiff> {
+        let expected = txtar::to_tree(expected);
+        let actual = self
+            .collapse_root(tree::read(self.path())?)
+            .into_iter()
+            .map(|(path, entry)| match entry {
+                // `[text file, N bytes]` counts the content as is.
+                Entry::File(content) if !matches!(expected.get(&path), Some(Entry::Text(_))) => {
+                    (path, Entry::File(txtar::fix_newline(content)))
+                }
+                entry => (path, entry),
+            })
+            .collect();
+        Ok(TreeDiff::new(&expected, &actual))
+    }
+}
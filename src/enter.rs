@@ -0,0 +1,90 @@
+//! Temporarily switching the process working directory into the
+//! fixture.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use std::env;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+/// Serializes working directory changes, which are process-wide.
+static CURRENT_DIR: Mutex<()> = Mutex::new(());
+
+/// Restores the previous working directory when dropped, see
+/// [`TestFiles::enter`].
+#[must_use = "the previous working directory is restored when the guard is dropped"]
+#[derive(Debug)]
+pub struct EnterGuard<'a> {
+    previous: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+    _files: PhantomData<&'a TestFiles>,
+}
+
+impl Drop for EnterGuard<'_> {
+    fn drop(&mut self) {
+        let _ = env::set_current_dir(&self.previous);
+    }
+}
+
+impl TestFiles {
+    /// Makes the temporary directory the working directory of the
+    /// process until the returned guard is dropped.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.txt", "ok");
+    ///
+    /// let _guard = temp_dir.enter();
+    /// assert_eq!(fs::read_to_string("a.txt").unwrap(), "ok");
+    /// ```
+    pub fn enter(&self) -> EnterGuard<'_> {
+        self.try_enter().unwrap()
+    }
+
+    /// Tries to make the temporary directory the working directory of
+    /// the process until the returned guard is dropped, at which point
+    /// the previous working directory is restored.
+    ///
+    /// As the working directory is shared by every thread, guards are
+    /// serialized by a global lock: tests using this wait for each
+    /// other, and a thread entering twice without dropping the first
+    /// guard deadlocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::env;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let previous = env::current_dir().unwrap();
+    /// let temp_dir = test_files::TestFiles::new();
+    /// {
+    ///     let _guard = temp_dir.try_enter()?;
+    ///     assert_eq!(
+    ///         env::current_dir().unwrap().canonicalize().unwrap(),
+    ///         temp_dir.path().canonicalize().unwrap()
+    ///     );
+    /// }
+    /// assert_eq!(env::current_dir().unwrap(), previous);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_enter(&self) -> Result<EnterGuard<'_>> {
+        let lock = CURRENT_DIR
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let change_dir_error = || TestFilesError::io(Operation::ChangeDir, ".", self.path());
+        let previous = env::current_dir().map_err(change_dir_error())?;
+        env::set_current_dir(self.path()).map_err(change_dir_error())?;
+        Ok(EnterGuard {
+            previous,
+            _lock: lock,
+            _files: PhantomData,
+        })
+    }
+}
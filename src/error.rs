@@ -0,0 +1,120 @@
+//! Errors carrying the operation and path which failed.
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// What was being done to a path when a [`TestFilesError`] occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    CreateTempDir,
+    CreateDir,
+    Write,
+    Read,
+    ReadDir,
+    Remove,
+    Resolve,
+    Symlink,
+    HardLink,
+    Chmod,
+    Chown,
+    SetTimes,
+    ChangeDir,
+    Spawn,
+    Serialize,
+    Deserialize,
+    Copy,
+    Render,
+    Unpack,
+    Pack,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CreateTempDir => "create temporary directory in",
+            Self::CreateDir => "create directory",
+            Self::Write => "write",
+            Self::Read => "read",
+            Self::ReadDir => "read directory",
+            Self::Remove => "remove",
+            Self::Resolve => "resolve",
+            Self::Symlink => "create symlink",
+            Self::HardLink => "create hard link",
+            Self::Chmod => "change mode of",
+            Self::Chown => "change owner of",
+            Self::SetTimes => "set timestamps of",
+            Self::ChangeDir => "change working directory to",
+            Self::Spawn => "run",
+            Self::Serialize => "serialize",
+            Self::Deserialize => "deserialize",
+            Self::Copy => "copy into",
+            Self::Render => "render template",
+            Self::Unpack => "unpack",
+            Self::Pack => "pack",
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TestFilesError {
+    #[error("Could not {operation} `{}` ({}): {source}", path.display(), absolute.display())]
+    IoError {
+        operation: Operation,
+        path: PathBuf,
+        absolute: PathBuf,
+        source: io::Error,
+    },
+    #[error(
+        "Could not {operation} `{}` ({}): path escapes the temporary root",
+        path.display(),
+        absolute.display()
+    )]
+    PathEscapesRoot {
+        operation: Operation,
+        path: PathBuf,
+        absolute: PathBuf,
+    },
+}
+
+impl TestFilesError {
+    /// Returns a function wrapping an [`io::Error`] with the failed
+    /// operation and path, for use with [`Result::map_err`].
+    pub(crate) fn io(
+        operation: Operation,
+        path: impl AsRef<Path>,
+        absolute: impl AsRef<Path>,
+    ) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_owned();
+        let absolute = absolute.as_ref().to_owned();
+        move |source| Self::IoError {
+            operation,
+            path,
+            absolute,
+            source,
+        }
+    }
+
+    /// Returns the operation which failed.
+    pub fn operation(&self) -> Operation {
+        match self {
+            Self::IoError { operation, .. } | Self::PathEscapesRoot { operation, .. } => *operation,
+        }
+    }
+
+    /// Returns the path which the operation was given, usually
+    /// relative to the temporary root.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::IoError { path, .. } | Self::PathEscapesRoot { path, .. } => path,
+        }
+    }
+
+    /// Returns the absolute path the operation was applied to.
+    pub fn absolute(&self) -> &Path {
+        match self {
+            Self::IoError { absolute, .. } | Self::PathEscapesRoot { absolute, .. } => absolute,
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! Serializing values straight into fixture files, and deserializing
+//! them back out, behind the `json`, `toml` and `yaml` features.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+impl TestFiles {
+    /// Creates a file under temporary directory holding `value`
+    /// serialized as pretty-printed JSON.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use std::fs;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Config {
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.json("config.json", &Config { verbose: true });
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("config.json")).unwrap();
+    /// assert_eq!(written_content, "{\n  \"verbose\": true\n}");
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json<T: Serialize + ?Sized>(&self, path: impl AsRef<Path>, value: &T) -> &Self {
+        self.try_json(path, value).unwrap()
+    }
+
+    /// Reads the file at `path`, relative to the temporary directory,
+    /// and deserializes it from JSON.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("config.json", r#"{"verbose": true}"#);
+    ///
+    /// let config: Config = temp_dir.read_json("config.json");
+    /// assert!(config.verbose);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn read_json<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> T {
+        self.try_read_json(path).unwrap()
+    }
+
+    /// Reads the file at `path`, relative to the temporary directory,
+    /// and deserializes it from TOML.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("config.toml", "verbose = true\n");
+    ///
+    /// let config: Config = temp_dir.read_toml("config.toml");
+    /// assert!(config.verbose);
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn read_toml<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> T {
+        self.try_read_toml(path).unwrap()
+    }
+
+    /// Reads the file at `path`, relative to the temporary directory,
+    /// and deserializes it from YAML.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Index {
+    ///     version: u32,
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a/b/index.yml", "---\nversion: 3\n");
+    ///
+    /// let index: Index = temp_dir.read_yaml("a/b/index.yml");
+    /// assert_eq!(index.version, 3);
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn read_yaml<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> T {
+        self.try_read_yaml(path).unwrap()
+    }
+
+    /// Creates a file under temporary directory holding `value`
+    /// serialized as TOML.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use std::fs;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Config {
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.toml("config.toml", &Config { verbose: true });
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("config.toml")).unwrap();
+    /// assert_eq!(written_content, "verbose = true\n");
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn toml<T: Serialize + ?Sized>(&self, path: impl AsRef<Path>, value: &T) -> &Self {
+        self.try_toml(path, value).unwrap()
+    }
+
+    /// Tries to create a file under temporary directory holding
+    /// `value` serialized as pretty-printed JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::fs;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let mut versions = BTreeMap::new();
+    /// versions.insert("serde", "1.0");
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_json("a/versions.json", &versions)?;
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("a").join("versions.json")).unwrap();
+    /// assert_eq!(written_content, "{\n  \"serde\": \"1.0\"\n}");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn try_json<T: Serialize + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        value: &T,
+    ) -> Result<&Self> {
+        self.try_serialized(path.as_ref(), serde_json::to_string_pretty(value))
+    }
+
+    fn try_deserialized<T, E>(
+        &self,
+        path: &Path,
+        deserialize: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Result<T>
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        deserialize(&self.try_read(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+            .map_err(TestFilesError::io(
+                Operation::Deserialize,
+                path,
+                self.path().join(path),
+            ))
+    }
+
+    /// Tries to read the file at `path`, relative to the temporary
+    /// directory, and deserialize it from JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use test_files::Operation;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_json("versions.json", &[("serde", "1.0")])?;
+    /// temp_dir.try_file("broken.json", "{")?;
+    ///
+    /// let versions: Vec<(String, String)> = temp_dir.try_read_json("versions.json")?;
+    /// assert_eq!(versions, [("serde".to_string(), "1.0".to_string())]);
+    ///
+    /// let error = temp_dir.try_read_json::<BTreeMap<String, String>>("broken.json").unwrap_err();
+    /// assert_eq!(error.operation(), Operation::Deserialize);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn try_read_json<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> Result<T> {
+        self.try_deserialized(path.as_ref(), |content| serde_json::from_str(content))
+    }
+
+    /// Tries to read the file at `path`, relative to the temporary
+    /// directory, and deserialize it from TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("config.toml", "verbose = true\n")?;
+    ///
+    /// let config: BTreeMap<String, bool> = temp_dir.try_read_toml("config.toml")?;
+    /// assert_eq!(config["verbose"], true);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn try_read_toml<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> Result<T> {
+        self.try_deserialized(path.as_ref(), |content| toml::from_str(content))
+    }
+
+    /// Tries to read the file at `path`, relative to the temporary
+    /// directory, and deserialize it from YAML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_yaml("list.yml", &["a", "b"])?;
+    ///
+    /// let list: Vec<String> = temp_dir.try_read_yaml("list.yml")?;
+    /// assert_eq!(list, ["a", "b"]);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn try_read_yaml<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> Result<T> {
+        self.try_deserialized(path.as_ref(), |content| serde_norway::from_str(content))
+    }
+
+    fn try_serialized<E>(&self, path: &Path, serialized: Result<String, E>) -> Result<&Self>
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        let content = serialized
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+            .map_err(TestFilesError::io(
+                Operation::Serialize,
+                path,
+                self.path().join(path),
+            ))?;
+        self.try_file(path, content)
+    }
+
+    /// Tries to create a file under temporary directory holding
+    /// `value` serialized as TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use test_files::Operation;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let mut config = BTreeMap::new();
+    /// config.insert("verbose", true);
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_toml("config.toml", &config)?;
+    ///
+    /// // TOML documents must be tables
+    /// let error = temp_dir.try_toml("bare.toml", &true).unwrap_err();
+    /// assert_eq!(error.operation(), Operation::Serialize);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn try_toml<T: Serialize + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        value: &T,
+    ) -> Result<&Self> {
+        self.try_serialized(path.as_ref(), toml::to_string(value))
+    }
+
+    /// Tries to create a file under temporary directory holding
+    /// `value` serialized as YAML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use std::fs;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Index {
+    ///     version: u32,
+    /// }
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_yaml("a/b/index.yml", &Index { version: 3 })?;
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("a/b/index.yml")).unwrap();
+    /// assert_eq!(written_content, "version: 3\n");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn try_yaml<T: Serialize + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        value: &T,
+    ) -> Result<&Self> {
+        self.try_serialized(path.as_ref(), serde_norway::to_string(value))
+    }
+
+    /// Creates a file under temporary directory holding `value`
+    /// serialized as YAML.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.yaml("list.yml", &["a", "b"]);
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("list.yml")).unwrap();
+    /// assert_eq!(written_content, "- a\n- b\n");
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn yaml<T: Serialize + ?Sized>(&self, path: impl AsRef<Path>, value: &T) -> &Self {
+        self.try_yaml(path, value).unwrap()
+    }
+}
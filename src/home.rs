@@ -0,0 +1,174 @@
+//! Hermetic home directories following the XDG base directory layout.
+use crate::{Operation, Result, SubDir, TestFiles, TestFilesError};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Returns the environment variables pointing at the home directory
+/// `home` and its XDG base directories.
+fn envs(home: &Path) -> BTreeMap<&'static str, PathBuf> {
+    let mut envs = BTreeMap::new();
+    envs.insert("HOME", home.to_owned());
+    envs.insert("XDG_CACHE_HOME", home.join(".cache"));
+    envs.insert("XDG_CONFIG_HOME", home.join(".config"));
+    envs.insert("XDG_DATA_HOME", home.join(".local").join("share"));
+    envs
+}
+
+/// A home directory inside a [`TestFiles`], with XDG config, cache and
+/// data directories.  Dereferences to a [`SubDir`] for populating it.
+/// See [`TestFiles::home`].
+#[derive(Clone, Debug)]
+pub struct Home<'a>(SubDir<'a>);
+
+impl Home<'_> {
+    /// Returns the path of `XDG_CACHE_HOME`, `.cache` in the home
+    /// directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.home();
+    ///
+    /// assert_eq!(home.cache(), temp_dir.path().join("home").join(".cache"));
+    /// ```
+    pub fn cache(&self) -> PathBuf {
+        self.path().join(".cache")
+    }
+
+    /// Returns the path of `XDG_CONFIG_HOME`, `.config` in the home
+    /// directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.home();
+    ///
+    /// assert_eq!(home.config(), temp_dir.path().join("home").join(".config"));
+    /// ```
+    pub fn config(&self) -> PathBuf {
+        self.path().join(".config")
+    }
+
+    /// Returns the path of `XDG_DATA_HOME`, `.local/share` in the home
+    /// directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.home();
+    ///
+    /// assert_eq!(home.data(), temp_dir.path().join("home").join(".local").join("share"));
+    /// ```
+    pub fn data(&self) -> PathBuf {
+        self.path().join(".local").join("share")
+    }
+
+    /// Returns the environment variables pointing at this home:
+    /// `HOME`, `XDG_CACHE_HOME`, `XDG_CONFIG_HOME` and `XDG_DATA_HOME`,
+    /// e.g. for [`Command::envs`](std::process::Command::envs).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::process::Command;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.home();
+    ///
+    /// let mut command = Command::new("my-tool");
+    /// command.envs(home.envs());
+    /// assert_eq!(home.envs()["HOME"], home.path());
+    /// ```
+    pub fn envs(&self) -> BTreeMap<&'static str, PathBuf> {
+        envs(self.path())
+    }
+}
+
+impl<'a> Deref for Home<'a> {
+    type Target = SubDir<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TestFiles {
+    /// Creates a hermetic home directory, `home` under the temporary
+    /// directory, with empty `.config`, `.cache` and `.local/share`
+    /// directories.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.home();
+    /// home.file(".config/app/config.toml", "verbose = true\n");
+    ///
+    /// let file_path = home.config().join("app").join("config.toml");
+    /// assert_eq!(fs::read_to_string(file_path).unwrap(), "verbose = true\n");
+    /// ```
+    pub fn home(&self) -> Home<'_> {
+        self.try_home().unwrap()
+    }
+
+    /// Tries to create a hermetic home directory, `home` under the
+    /// temporary directory, with empty `.config`, `.cache` and
+    /// `.local/share` directories.  Calling this again returns the
+    /// same home, leaving its content alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let home = temp_dir.try_home()?;
+    /// home.try_file(".gitconfig", "[user]\n")?;
+    ///
+    /// assert!(home.data().is_dir());
+    /// assert!(temp_dir.path().join("home").join(".gitconfig").is_file());
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_home(&self) -> Result<Home<'_>> {
+        let home = self.try_sub("home")?;
+        home.try_dir(".config")?
+            .try_dir(".cache")?
+            .try_dir(".local/share")?;
+        Ok(Home(home))
+    }
+
+    /// Returns the environment variables pointing at an empty home
+    /// directory beside, rather than inside, the temporary directory,
+    /// creating it on first use so that it stays out of the tree
+    /// under test.
+    pub(crate) fn try_hermetic_envs(&self) -> Result<BTreeMap<&'static str, PathBuf>> {
+        let home = match self.hermetic_home.get() {
+            Some(home) => home,
+            None => {
+                let parent = self.path().parent().unwrap_or_else(|| self.path());
+                let home = tempfile::Builder::new()
+                    .prefix(".home")
+                    .tempdir_in(parent)
+                    .map_err(TestFilesError::io(Operation::CreateTempDir, parent, parent))?;
+                // A concurrent caller may have won the race, in which
+                // case this home is dropped in favour of theirs.
+                let _ = self.hermetic_home.set(home);
+                self.hermetic_home.get().unwrap()
+            }
+        };
+        let envs = envs(home.path());
+        for (_, dir) in envs.iter().filter(|(name, _)| **name != "HOME") {
+            fs::create_dir_all(dir).map_err(TestFilesError::io(Operation::CreateDir, dir, dir))?;
+        }
+        Ok(envs)
+    }
+}
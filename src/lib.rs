@@ -5,7 +5,9 @@
 //! and content.
 //!
 //! A temporary directory is created on instantiation, and torn
-//! down when the returned object falls out of scope.
+//! down when the returned object falls out of scope.  Set
+//! `TEST_FILES_KEEP=on-failure` to keep it around for inspection
+//! when a test panics.
 //!
 //! # Example
 //!
@@ -21,35 +23,138 @@
 //! let written_content = std::fs::read_to_string(file_path).unwrap();
 //! assert_eq!(written_content, "ok");
 //!
-//! let file_path = temp_dir.path().join("b").join("c").join("d.txt");
-//! let written_content = std::fs::read_to_string(file_path).unwrap();
-//! assert_eq!(written_content, "fine");
+//! assert_eq!(temp_dir.read("b/c/d.txt"), "fine");
 //! ```
 //!
 //! The pain of creating intermediate directories is abstracted
 //! away, so you can just write relative paths, content, and
 //! use the created files in tests or otherwise.  The root of
-//! the temporary directory is exposed by the `.path()` method.
-use std::path::{Path, PathBuf};
-use tempfile::{tempdir, TempDir};
-use thiserror::Error;
-use touch::file;
+//! the temporary directory is exposed by the `.path()` method,
+//! and files can be read back relative to it with `.read()`.
+//!
+//! Whole fixture trees can be declared at once with the [`tree!`] macro,
+//! or loaded from a txtar archive with [`TestFiles::from_txtar`].  The
+//! reverse, [`TestFiles::to_txtar`], renders a tree for comparisons,
+//! and [`TestFiles::assert_tree_eq`] checks a tree against an expected
+//! archive.  [`TestFiles::assert_snapshot`] does the same against a
+//! golden directory, which is rewritten when `TEST_FILES_BLESS=1`.
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod archive;
+mod builder;
+mod command;
+mod copy;
+mod diff;
+mod enter;
+mod error;
+#[cfg(any(feature = "json", feature = "toml", feature = "yaml"))]
+mod formats;
+mod home;
+mod link;
+mod macros;
+#[cfg(unix)]
+mod permissions;
+mod root;
+mod snapshot;
+mod sub;
+mod template;
+mod time;
+mod tree;
+mod txtar;
+
+pub use builder::Builder;
+pub use command::CommandOutput;
+pub use diff::TreeDiff;
+pub use enter::EnterGuard;
+pub use error::{Operation, TestFilesError};
+pub use home::Home;
+pub use snapshot::BLESS_VAR;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::SystemTime;
+pub use sub::SubDir;
+use tempfile::TempDir;
+pub use time::ago;
 
 pub type Result<T, E = TestFilesError> = core::result::Result<T, E>;
 
-#[derive(Error, Debug)]
-pub enum TestFilesError {
-    #[error("Path error `{path:?}`")]
-    PathError { path: String },
-    #[error(transparent)]
-    FileWriteError(#[from] touch::Error),
-    #[error(transparent)]
-    TempDirError(#[from] std::io::Error),
+/// Environment variable selecting when temporary directories are
+/// kept rather than removed: `never` (the default), `on-failure` or
+/// `always`.
+pub const KEEP_VAR: &str = "TEST_FILES_KEEP";
+
+/// When a temporary directory should outlive its [`TestFiles`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Keep {
+    /// Always remove the directory.
+    #[default]
+    Never,
+    /// Keep the directory if it is dropped while the thread panics,
+    /// e.g. because a test assertion failed.
+    OnFailure,
+    /// Never remove the directory.
+    Always,
+}
+
+impl Keep {
+    fn from_env() -> Self {
+        match env::var(KEEP_VAR).as_deref() {
+            Ok("on-failure") => Self::OnFailure,
+            Ok("always") => Self::Always,
+            _ => Self::Never,
+        }
+    }
 }
 
-pub struct TestFiles(TempDir);
+#[derive(Debug)]
+pub struct TestFiles {
+    dir: TempDir,
+    keep: Keep,
+    epoch: Option<SystemTime>,
+    root_token: Option<String>,
+    /// The home directory of hermetic commands, kept outside of `dir`.
+    hermetic_home: OnceLock<TempDir>,
+}
 
 impl TestFiles {
+    /// Returns a [`Builder`] for customising where and how the
+    /// temporary directory is created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let parent = test_files::TestFiles::new();
+    /// let temp_dir = test_files::TestFiles::builder()
+    ///     .in_dir(parent.path())
+    ///     .prefix("my-test-")
+    ///     .build();
+    ///
+    /// assert_eq!(temp_dir.path().parent(), Some(parent.path()));
+    /// ```
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Creates a directory, and any missing parents, under temporary
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.dir("a/b").dir("c");
+    ///
+    /// assert!(temp_dir.path().join("a").join("b").is_dir());
+    /// assert!(temp_dir.path().join("c").is_dir());
+    /// ```
+    pub fn dir(&self, path: impl AsRef<Path>) -> &Self {
+        self.try_dir(path).unwrap()
+    }
+
     /// Creates a plain file under temporary directory, with specified
     /// content.
     ///
@@ -71,7 +176,7 @@ impl TestFiles {
     /// let written_content = fs::read_to_string(file_path).unwrap();
     /// assert_eq!(written_content, "fine");
     /// ```
-    pub fn file(&self, path: &str, content: &str) -> &Self {
+    pub fn file(&self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> &Self {
         self.try_file(path, content).unwrap()
     }
 
@@ -101,21 +206,112 @@ impl TestFiles {
     /// assert!(temp_dir.path().is_dir());
     /// ```
     pub fn path(&self) -> &Path {
-        self.0.path()
+        self.dir.path()
+    }
+
+    /// Reads the file at `path`, relative to the temporary directory,
+    /// as a string.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a/b/c.txt", "ok");
+    ///
+    /// assert_eq!(temp_dir.read("a/b/c.txt"), "ok");
+    /// ```
+    pub fn read(&self, path: impl AsRef<Path>) -> String {
+        self.try_read(path).unwrap()
+    }
+
+    /// Reads the file at `path`, relative to the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.bin", [0xde, 0xad]);
+    ///
+    /// assert_eq!(temp_dir.read_bytes("a.bin"), [0xde, 0xad]);
+    /// ```
+    pub fn read_bytes(&self, path: impl AsRef<Path>) -> Vec<u8> {
+        self.try_read_bytes(path).unwrap()
     }
 
-    fn slash(&self, relative_path: &str) -> PathBuf {
-        self.path().join(relative_path)
+    /// Resolves `relative_path` under the temporary root, refusing
+    /// absolute paths, `..` traversal above the root and symlinks
+    /// leading outside of it (or which cannot be resolved).
+    fn slash(&self, relative_path: &Path, operation: Operation) -> Result<PathBuf> {
+        let escapes = || TestFilesError::PathEscapesRoot {
+            operation,
+            path: relative_path.to_owned(),
+            absolute: self.path().join(relative_path),
+        };
+        let normalized = normalize(relative_path).ok_or_else(escapes)?;
+
+        let root = self.path().canonicalize().map_err(TestFilesError::io(
+            Operation::Resolve,
+            ".",
+            self.path(),
+        ))?;
+        let mut prefix = self.path().to_owned();
+        for component in normalized.components() {
+            prefix.push(component);
+            match fs::symlink_metadata(&prefix) {
+                Ok(metadata) if metadata.file_type().is_symlink() => match prefix.canonicalize() {
+                    Ok(target) if target.starts_with(&root) => {}
+                    _ => return Err(escapes()),
+                },
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(self.path().join(normalized))
+    }
+
+    /// Tries to create a directory, and any missing parents, under
+    /// temporary directory.  Existing directories are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_dir("a/b")?.try_file("a/c.txt", "ok")?;
+    ///
+    /// assert!(temp_dir.path().join("a").join("b").is_dir());
+    /// temp_dir.assert_tree_eq("-- a/b/ --\n-- a/c.txt --\nok\n");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_dir(&self, path: impl AsRef<Path>) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::CreateDir)?;
+        fs::create_dir_all(&absolute).map_err(TestFilesError::io(
+            Operation::CreateDir,
+            path,
+            &absolute,
+        ))?;
+        Ok(self)
     }
 
     /// Tries to create a plain file under temporary directory
-    /// with specified content.
+    /// with specified content, which may be text or raw bytes.
+    ///
+    /// Paths which would escape the temporary directory, whether
+    /// absolute, through `..` or through a symlink, are refused
+    /// with [`TestFilesError::PathEscapesRoot`].
     ///
     /// # Examples
     ///
     /// ```
     /// use indoc::indoc;
     /// use std::fs;
+    /// use std::path::Path;
     ///
     /// # fn main() -> test_files::Result<()> {
     /// let temp_dir = test_files::TestFiles::new();
@@ -129,22 +325,30 @@ impl TestFiles {
     /// let file_path = temp_dir.path().join("b").join("c").join("d.txt");
     /// let written_content = fs::read_to_string(file_path).unwrap();
     /// assert_eq!(written_content, "fine");
+    ///
+    /// temp_dir.try_file(Path::new("e.bin"), [0xde, 0xad, 0xbe, 0xef])?;
+    /// let written_content = fs::read(temp_dir.path().join("e.bin")).unwrap();
+    /// assert_eq!(written_content, [0xde, 0xad, 0xbe, 0xef]);
     /// #   Ok(())
     /// # }
     /// ```
-    pub fn try_file(&self, path: &str, content: &str) -> Result<&Self> {
-        file::write(
-            self.slash(path).to_str().ok_or(TestFilesError::PathError {
-                path: path.to_string(),
-            })?,
-            content,
-            true,
-        )?;
+    pub fn try_file(&self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::Write)?;
+        create_parent(path, &absolute)?;
+        fs::write(&absolute, content).map_err(TestFilesError::io(
+            Operation::Write,
+            path,
+            &absolute,
+        ))?;
+        self.stamp_epoch(path, &absolute)?;
         Ok(self)
     }
 
     /// Tries to create a new temporary directory that is
-    /// removed when it goes out of scope.
+    /// removed when it goes out of scope, unless the
+    /// `TEST_FILES_KEEP` environment variable says otherwise
+    /// (see [`Keep`]).
     ///
     /// # Examples
     ///
@@ -155,10 +359,105 @@ impl TestFiles {
     /// assert!(temp_dir.unwrap().path().is_dir());
     /// ```
     pub fn try_new() -> Result<Self> {
-        Ok(Self(tempdir()?))
+        Self::builder().try_build()
+    }
+
+    /// Tries to read the file at `path`, relative to the temporary
+    /// directory, as a string.  Content which is not valid UTF-8 is
+    /// an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a/b/c.txt", "ok")?;
+    ///
+    /// assert_eq!(temp_dir.try_read("a/b/c.txt")?, "ok");
+    /// assert!(temp_dir.try_read("missing.txt").is_err());
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_read(&self, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::Read)?;
+        fs::read_to_string(&absolute).map_err(TestFilesError::io(Operation::Read, path, &absolute))
+    }
+
+    /// Tries to read the file at `path`, relative to the temporary
+    /// directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.bin", [0xff, 0xfe])?;
+    ///
+    /// assert_eq!(temp_dir.try_read_bytes("a.bin")?, [0xff, 0xfe]);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_read_bytes(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::Read)?;
+        fs::read(&absolute).map_err(TestFilesError::io(Operation::Read, path, &absolute))
     }
 }
 
+impl Drop for TestFiles {
+    fn drop(&mut self) {
+        let keep = match self.keep {
+            Keep::Never => false,
+            Keep::OnFailure => thread::panicking(),
+            Keep::Always => true,
+        };
+        if keep {
+            self.dir.disable_cleanup(true);
+            eprintln!(
+                "test-files: keeping temporary directory {}",
+                self.path().display()
+            );
+        } else if fs::remove_dir_all(self.path()).is_err() {
+            // Restricted modes, whoever set them, are only restored
+            // when they get in the way, sparing large trees a walk.
+            #[cfg(unix)]
+            {
+                permissions::make_removable(self.path());
+                let _ = fs::remove_dir_all(self.path());
+            }
+        }
+    }
+}
+
+/// Lexically resolves `.` and `..` in a relative path, returning
+/// `None` if it is absolute or climbs above its starting point.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir if normalized.pop() => {}
+            _ => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// Creates the missing parent directories of `absolute`, the resolved
+/// form of `path`.
+fn create_parent(path: &Path, absolute: &Path) -> Result<()> {
+    if let Some(parent) = absolute.parent() {
+        fs::create_dir_all(parent).map_err(TestFilesError::io(
+            Operation::CreateDir,
+            path.parent().unwrap_or(path),
+            parent,
+        ))?;
+    }
+    Ok(())
+}
+
 impl Default for TestFiles {
     fn default() -> Self {
         Self::new()
@@ -198,4 +497,271 @@ mod tests {
         assert!(!tmp_path.unwrap().is_dir());
         Ok(())
     }
+
+    #[test]
+    fn tree_macro_nests_directories() -> Result<()> {
+        let files = crate::tree! {
+            "a" => {
+                "b" => {
+                    "c.txt" => "deep"
+                },
+                "d.txt" => "shallow"
+            },
+            "e.txt" => "top"
+        };
+
+        assert_eq!(fs::read_to_string(files.path().join("a/b/c.txt"))?, "deep");
+        assert_eq!(fs::read_to_string(files.path().join("a/d.txt"))?, "shallow");
+        assert_eq!(fs::read_to_string(files.path().join("e.txt"))?, "top");
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "missing file: b.txt")]
+    fn assert_tree_eq_reports_missing_files() {
+        let files = TestFiles::new();
+        files.file("a.txt", "ok");
+
+        files.assert_tree_eq("-- a.txt --\nok\n-- b.txt --\n");
+    }
+
+    #[test]
+    fn keeps_directory_on_failure() -> Result<()> {
+        let mut files = TestFiles::new();
+        files.keep = Keep::OnFailure;
+        let tmp_path = files.path().to_owned();
+
+        let result = std::panic::catch_unwind(move || {
+            let _files = files;
+            panic!("test failure");
+        });
+
+        assert!(result.is_err());
+        assert!(tmp_path.is_dir());
+        fs::remove_dir_all(tmp_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_paths_escaping_root() {
+        let files = TestFiles::new();
+        files.file("a/../b.txt", "inside");
+        assert!(files.path().join("b.txt").is_file());
+
+        for path in ["../outside.txt", "a/../../outside.txt", "/tmp/outside.txt"] {
+            assert!(matches!(
+                files.try_file(path, "outside"),
+                Err(TestFilesError::PathEscapesRoot { .. })
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_symlinks_escaping_root() -> Result<()> {
+        let outside = TestFiles::new();
+        let files = TestFiles::new();
+        std::os::unix::fs::symlink(outside.path(), files.path().join("link"))?;
+        std::os::unix::fs::symlink("a/b", files.path().join("dangling"))?;
+
+        assert!(matches!(
+            files.try_file("link/x.txt", "outside"),
+            Err(TestFilesError::PathEscapesRoot { .. })
+        ));
+        assert!(matches!(
+            files.try_file("dangling", "outside"),
+            Err(TestFilesError::PathEscapesRoot { .. })
+        ));
+        assert!(fs::read_dir(outside.path())?.next().is_none());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_non_utf8_paths() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let files = TestFiles::new();
+        let name = OsStr::from_bytes(b"caf\xe9.bin");
+        files.file(name, b"\x00\xff");
+
+        assert_eq!(fs::read(files.path().join(name))?, b"\x00\xff");
+        Ok(())
+    }
+
+    #[test]
+    fn errors_describe_operation_and_paths() {
+        let files = TestFiles::new();
+        files.file("a", "file, not directory");
+
+        let error = files.try_file("a/b.txt", "content").unwrap_err();
+        assert_eq!(error.operation(), Operation::CreateDir);
+        assert_eq!(error.path(), Path::new("a"));
+        assert_eq!(error.absolute(), files.path().join("a"));
+        assert!(error.to_string().starts_with(&format!(
+            "Could not create directory `a` ({})",
+            files.path().join("a").display()
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_symlinks_without_following_them() {
+        let files = TestFiles::new();
+        files
+            .file("a.txt", "ok\n")
+            .symlink("dangling", "missing.txt")
+            .symlink("loop", "loop");
+
+        assert_eq!(
+            files.to_txtar(),
+            indoc! {"
+                -- a.txt --
+                ok
+                -- dangling --
+                [symlink to missing.txt]
+                -- loop --
+                [symlink to loop]
+            "}
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn removes_directories_without_permissions() {
+        let tmp_path: PathBuf;
+        {
+            let files = TestFiles::new();
+            tmp_path = files.path().to_owned();
+            files
+                .file("locked/deeper/a.txt", "ok")
+                .chmod("locked/deeper", 0o000)
+                .chmod("locked", 0o000);
+        }
+        assert!(!tmp_path.exists());
+    }
+
+    #[test]
+    fn substitutes_root_token_when_loading_and_exporting() -> Result<()> {
+        let fixture = TestFiles::new();
+        fixture
+            .file("config.toml", "cache = \"{root}/cache\"")
+            .file("data.bin", [0xff, b'{']);
+
+        let files = TestFiles::builder().root_token("{root}").build();
+        files.copy_in(fixture.path(), "");
+
+        let root = fs::canonicalize(files.path())?;
+        assert_eq!(
+            files.read("config.toml"),
+            format!("cache = \"{}/cache\"", files.path().display())
+        );
+        assert_eq!(files.read_bytes("data.bin"), [0xff, b'{']);
+        fs::remove_file(files.path().join("data.bin"))?;
+        files.file("out.log", format!("wrote {}/cache", root.display()));
+        files.assert_tree_eq(indoc! {"
+            -- config.toml --
+            cache = \"{root}/cache\"
+            -- out.log --
+            wrote {root}/cache
+        "});
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_loading_binary_placeholders() {
+        let files = TestFiles::new();
+
+        let error = files
+            .try_txtar("-- a.bin --\n[binary file, 2 bytes]\n")
+            .unwrap_err();
+        assert_eq!(error.operation(), Operation::Write);
+        assert!(!files.path().join("a.bin").exists());
+    }
+
+    #[test]
+    fn diffs_against_exported_binary_files_and_symlinks() -> Result<()> {
+        let files = TestFiles::new();
+        files
+            .file("a.bin", [0xff, 0xfe])
+            .file("b.txt", "ok")
+            .symlink("l", "a.bin");
+
+        assert!(files.diff_tree(&files.to_txtar())?.is_empty());
+
+        let diff = files.diff_tree(indoc! {"
+            -- a.bin --
+            [binary file, 3 bytes]
+            -- b.txt --
+            ok
+            -- l --
+            [symlink to b.txt]
+        "})?;
+        assert_eq!(
+            diff.changed
+                .iter()
+                .map(|(path, _)| path.as_path())
+                .collect::<Vec<_>>(),
+            [Path::new("a.bin"), Path::new("l")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_running_commands_outside_the_fixture() {
+        use std::process::Command;
+
+        let files = TestFiles::new();
+        let other = TestFiles::new();
+        let mut parent = files.command("true");
+        parent.current_dir(files.path().join(".."));
+
+        for command in [
+            &mut other.command("true"),
+            &mut parent,
+            &mut Command::new("true"),
+        ] {
+            assert!(matches!(
+                files.try_run(command),
+                Err(TestFilesError::PathEscapesRoot {
+                    operation: Operation::Spawn,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn exports_text_which_would_not_load_back_as_placeholders() -> Result<()> {
+        let files = TestFiles::new();
+        files
+            .file("a.txt", "one\n-- b.txt --\ntwo\n")
+            .file("c.txt", "ok");
+
+        let archive = files.to_txtar();
+        assert_eq!(
+            archive,
+            "-- a.txt --\n[text file, 20 bytes]\n-- c.txt --\n[text file, 2 bytes]\n"
+        );
+        assert!(files.diff_tree(&archive)?.is_empty());
+        assert!(TestFiles::try_from_txtar(&archive).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn removes_directories_locked_by_the_code_under_test() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_path: PathBuf;
+        {
+            let files = TestFiles::new();
+            tmp_path = files.path().to_owned();
+            files.file("out/a.txt", "ok");
+            fs::set_permissions(files.path().join("out"), fs::Permissions::from_mode(0o555))?;
+        }
+        assert!(!tmp_path.exists());
+        Ok(())
+    }
 }
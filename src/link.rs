@@ -0,0 +1,118 @@
+//! Symlinks and hard links inside the fixture tree.
+use crate::{create_parent, tree, Operation, Result, TestFiles, TestFilesError};
+use std::fs;
+use std::path::Path;
+
+impl TestFiles {
+    /// Creates a hard link at `link` to the `existing` file, both
+    /// relative to the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.txt", "ok").hard_link("b/c.txt", "a.txt");
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("b").join("c.txt")).unwrap();
+    /// assert_eq!(written_content, "ok");
+    /// ```
+    pub fn hard_link(&self, link: impl AsRef<Path>, existing: impl AsRef<Path>) -> &Self {
+        self.try_hard_link(link, existing).unwrap()
+    }
+
+    /// Creates a symlink at `link`, relative to the temporary
+    /// directory, pointing to `target`.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.txt", "ok").symlink("b/c.txt", "../a.txt");
+    ///
+    /// let link_path = temp_dir.path().join("b").join("c.txt");
+    /// assert_eq!(fs::read_to_string(link_path).unwrap(), "ok");
+    /// ```
+    pub fn symlink(&self, link: impl AsRef<Path>, target: impl AsRef<Path>) -> &Self {
+        self.try_symlink(link, target).unwrap()
+    }
+
+    /// Tries to create a hard link at `link` to the `existing` file,
+    /// creating any missing parent directories of `link`.  Both paths
+    /// are relative to the temporary directory and may not escape it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_hard_link("b/c.txt", "a.txt")?;
+    /// temp_dir.try_file("a.txt", "changed")?;
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("b").join("c.txt")).unwrap();
+    /// assert_eq!(written_content, "changed");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_hard_link(
+        &self,
+        link: impl AsRef<Path>,
+        existing: impl AsRef<Path>,
+    ) -> Result<&Self> {
+        let link = link.as_ref();
+        let absolute = self.slash(link, Operation::HardLink)?;
+        let existing = self.slash(existing.as_ref(), Operation::HardLink)?;
+        create_parent(link, &absolute)?;
+        fs::hard_link(existing, &absolute).map_err(TestFilesError::io(
+            Operation::HardLink,
+            link,
+            &absolute,
+        ))?;
+        Ok(self)
+    }
+
+    /// Tries to create a symlink at `link`, relative to the temporary
+    /// directory, creating any missing parent directories.
+    ///
+    /// `target` is stored as given: it may be relative to the link's
+    /// directory, absolute, dangling or even point outside of the
+    /// temporary directory.  Only `link` itself must stay inside.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir
+    ///     .try_symlink("dangling", "does/not/exist")?
+    ///     .try_symlink("loop/a", "b")?
+    ///     .try_symlink("loop/b", "a")?;
+    ///
+    /// let link_path = temp_dir.path().join("dangling");
+    /// assert_eq!(fs::read_link(link_path).unwrap().to_str(), Some("does/not/exist"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_symlink(&self, link: impl AsRef<Path>, target: impl AsRef<Path>) -> Result<&Self> {
+        let link = link.as_ref();
+        let absolute = self.slash(link, Operation::Symlink)?;
+        create_parent(link, &absolute)?;
+        tree::symlink(target.as_ref(), &absolute).map_err(TestFilesError::io(
+            Operation::Symlink,
+            link,
+            &absolute,
+        ))?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,54 @@
+/// Declares a whole fixture tree in one expression.
+///
+/// Nested braces describe directories, `"name" => "content"` describes
+/// a file and `"name" => {}` an empty directory.  Every file is written
+/// through [`TestFiles::try_file`](crate::TestFiles::try_file) and every
+/// directory created through [`TestFiles::try_dir`](crate::TestFiles::try_dir).
+///
+/// Panics on failure
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+///
+/// let temp_dir = test_files::tree! {
+///     "Cargo.toml" => "[package]",
+///     "src" => {
+///         "main.rs" => "fn main() {}",
+///         "bin" => {},
+///     },
+/// };
+///
+/// let written_content = fs::read_to_string(temp_dir.path().join("src").join("main.rs")).unwrap();
+/// assert_eq!(written_content, "fn main() {}");
+/// assert!(temp_dir.path().join("src").join("bin").is_dir());
+/// ```
+#[macro_export]
+macro_rules! tree {
+    ($($entries:tt)*) => {{
+        let files = $crate::TestFiles::new();
+        $crate::__tree_entries!(files, ""; $($entries)*);
+        files
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tree_entries {
+    ($files:ident, $prefix:expr; ) => {};
+    ($files:ident, $prefix:expr; $name:literal => { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        {
+            let dir = format!("{}{}/", $prefix, $name);
+            $files.try_dir(&dir).unwrap();
+            $crate::__tree_entries!($files, dir; $($inner)*);
+        }
+        $crate::__tree_entries!($files, $prefix; $($($rest)*)?);
+    };
+    ($files:ident, $prefix:expr; $name:literal => $content:expr $(, $($rest:tt)*)?) => {
+        $files
+            .try_file(&format!("{}{}", $prefix, $name), $content)
+            .unwrap();
+        $crate::__tree_entries!($files, $prefix; $($($rest)*)?);
+    };
+}
@@ -0,0 +1,135 @@
+//! Unix permission and ownership control over fixture entries.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use std::fs::{self, Permissions};
+use std::os::unix::fs::{chown, PermissionsExt};
+use std::path::Path;
+
+impl TestFiles {
+    /// Sets the mode of `path`, relative to the temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("run.sh", "#!/bin/sh\n").chmod("run.sh", 0o755);
+    ///
+    /// let mode = fs::metadata(temp_dir.path().join("run.sh")).unwrap().permissions().mode();
+    /// assert_eq!(mode & 0o7777, 0o755);
+    /// ```
+    pub fn chmod(&self, path: impl AsRef<Path>, mode: u32) -> &Self {
+        self.try_chmod(path, mode).unwrap()
+    }
+
+    /// Sets the owning user and/or group of `path`, relative to the
+    /// temporary directory.  `None` leaves the respective id as is.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::os::unix::fs::MetadataExt;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let uid = fs::metadata(temp_dir.path()).unwrap().uid();
+    /// temp_dir.file("a.txt", "ok").chown("a.txt", Some(uid), None);
+    ///
+    /// assert_eq!(fs::metadata(temp_dir.path().join("a.txt")).unwrap().uid(), uid);
+    /// ```
+    pub fn chown(&self, path: impl AsRef<Path>, uid: Option<u32>, gid: Option<u32>) -> &Self {
+        self.try_chown(path, uid, gid).unwrap()
+    }
+
+    /// Tries to set the mode of `path`, relative to the temporary
+    /// directory, e.g. `0o444` for a read-only file, `0o000` for an
+    /// unreadable directory or `0o2775` for a setgid directory.
+    ///
+    /// The temporary directory is still removed on drop, however
+    /// restrictive the modes set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::os::unix::fs::PermissionsExt;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir
+    ///     .try_file("locked/a.txt", "ok")?
+    ///     .try_chmod("locked/a.txt", 0o444)?
+    ///     .try_chmod("locked", 0o500)?;
+    ///
+    /// let mode = fs::metadata(temp_dir.path().join("locked")).unwrap().permissions().mode();
+    /// assert_eq!(mode & 0o7777, 0o500);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_chmod(&self, path: impl AsRef<Path>, mode: u32) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::Chmod)?;
+        fs::set_permissions(&absolute, Permissions::from_mode(mode))
+            .map_err(TestFilesError::io(Operation::Chmod, path, &absolute))?;
+        Ok(self)
+    }
+
+    /// Tries to set the owning user and/or group of `path`, relative
+    /// to the temporary directory.  `None` leaves the respective id
+    /// as is.  Changing the owner usually requires root privileges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::os::unix::fs::MetadataExt;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let gid = fs::metadata(temp_dir.path()).unwrap().gid();
+    /// temp_dir.try_file("a.txt", "ok")?.try_chown("a.txt", None, Some(gid))?;
+    ///
+    /// assert_eq!(fs::metadata(temp_dir.path().join("a.txt")).unwrap().gid(), gid);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_chown(
+        &self,
+        path: impl AsRef<Path>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::Chown)?;
+        chown(&absolute, uid, gid).map_err(TestFilesError::io(
+            Operation::Chown,
+            path,
+            &absolute,
+        ))?;
+        Ok(self)
+    }
+}
+
+/// Gives the owner full access to `dir` and every directory below it,
+/// so that they can be removed.  Failures are ignored, leaving the
+/// removal itself to report them.
+pub(crate) fn make_removable(dir: &Path) {
+    let metadata = match fs::symlink_metadata(dir) {
+        Ok(metadata) if metadata.is_dir() => metadata,
+        _ => return,
+    };
+    let mode = metadata.permissions().mode();
+    if mode & 0o700 != 0o700 {
+        let _ = fs::set_permissions(dir, Permissions::from_mode(mode | 0o700));
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            make_removable(&entry.path());
+        }
+    }
+}
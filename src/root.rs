@@ -0,0 +1,54 @@
+//! Substitution of a sentinel token for the temporary directory's
+//! path, configured through [`Builder::root_token`](crate::Builder::root_token).
+use crate::tree::{Entry, Tree};
+use crate::TestFiles;
+use std::fs;
+
+/// Replaces every occurrence of `from` with `to` in `content`, which
+/// is returned unchanged unless it is valid UTF-8.
+fn replace(content: Vec<u8>, from: &str, to: &str) -> Vec<u8> {
+    match String::from_utf8(content) {
+        Ok(text) if text.contains(from) => text.replace(from, to).into_bytes(),
+        Ok(text) => text.into_bytes(),
+        Err(error) => error.into_bytes(),
+    }
+}
+
+impl TestFiles {
+    /// Replaces the root token in loaded fixture content with the
+    /// path of the temporary directory.
+    pub(crate) fn expand_root(&self, content: Vec<u8>) -> Vec<u8> {
+        match &self.root_token {
+            Some(token) => replace(content, token, &self.path().display().to_string()),
+            None => content,
+        }
+    }
+
+    /// Replaces the path of the temporary directory in the text files
+    /// of an exported tree with the root token.  The canonical path,
+    /// where it differs, is replaced too.
+    pub(crate) fn collapse_root(&self, tree: Tree) -> Tree {
+        let token = match &self.root_token {
+            Some(token) => token,
+            None => return tree,
+        };
+        let mut roots = vec![self.path().display().to_string()];
+        if let Ok(canonical) = fs::canonicalize(self.path()) {
+            roots.push(canonical.display().to_string());
+        }
+        // Longer paths go first, in case one contains the other.
+        roots.sort_by_key(|root| std::cmp::Reverse(root.len()));
+        roots.dedup();
+        tree.into_iter()
+            .map(|(path, entry)| match entry {
+                Entry::File(content) => {
+                    let content = roots
+                        .iter()
+                        .fold(content, |content, root| replace(content, root, token));
+                    (path, Entry::File(content))
+                }
+                entry => (path, entry),
+            })
+            .collect()
+    }
+}
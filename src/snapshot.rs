@@ -0,0 +1,141 @@
+//! Golden-directory snapshots, blessed by setting `TEST_FILES_BLESS`.
+use crate::tree::{self, Entry, Tree};
+use crate::{Operation, Result, TestFiles, TreeDiff};
+use std::env;
+use std::path::Path;
+
+/// Environment variable which, when set to anything but `0`, makes
+/// snapshot comparisons rewrite the golden directory instead.
+pub const BLESS_VAR: &str = "TEST_FILES_BLESS";
+
+fn bless() -> bool {
+    env::var_os(BLESS_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Drops empty directories, which git cannot check in, so that a
+/// blessed golden directory still matches after a fresh clone.
+fn without_empty_dirs(tree: Tree) -> Tree {
+    tree.into_iter()
+        .filter(|(_, entry)| *entry != Entry::Dir)
+        .collect()
+}
+
+impl TestFiles {
+    /// Asserts that the files under `path` (relative to the temporary
+    /// directory, `""` for all of it) match those in the `golden`
+    /// directory.  See [`TestFiles::diff_snapshot`].
+    ///
+    /// Panics with a description of every difference otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let golden = test_files::TestFiles::new();
+    /// golden.file("a.txt", "ok");
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("out/a.txt", "ok");
+    ///
+    /// temp_dir.assert_snapshot("out", golden.path());
+    /// ```
+    pub fn assert_snapshot(&self, path: impl AsRef<Path>, golden: impl AsRef<Path>) {
+        let golden = golden.as_ref();
+        let diff = self.diff_snapshot(path, golden).unwrap();
+        assert!(
+            diff.is_empty(),
+            "snapshot mismatch against {}, set {}=1 to update it:\n{}",
+            golden.display(),
+            BLESS_VAR,
+            diff
+        );
+    }
+
+    /// Compares the files under `path` (relative to the temporary
+    /// directory, `""` for all of it) against those in the `golden`
+    /// directory, typically checked in next to the tests.  A missing
+    /// golden directory counts as empty.  As git does not track empty
+    /// directories, they are ignored on both sides and never blessed.
+    ///
+    /// When the `TEST_FILES_BLESS` environment variable is set, the
+    /// golden directory is instead rewritten to match and an empty
+    /// diff is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let golden = test_files::TestFiles::new();
+    /// golden.try_file("a.txt", "ok")?;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_file("b.txt", "new")?;
+    ///
+    /// let diff = temp_dir.diff_snapshot("", golden.path())?;
+    /// assert_eq!(diff.unexpected, vec![PathBuf::from("b.txt")]);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn diff_snapshot(
+        &self,
+        path: impl AsRef<Path>,
+        golden: impl AsRef<Path>,
+    ) -> Result<TreeDiff> {
+        self.snapshot(path.as_ref(), golden.as_ref(), bless())
+    }
+
+    fn snapshot(&self, path: &Path, golden: &Path, bless: bool) -> Result<TreeDiff> {
+        let actual = without_empty_dirs(
+            self.collapse_root(tree::read(&self.slash(path, Operation::Read)?)?),
+        );
+        if bless {
+            tree::write(golden, &actual)?;
+            return Ok(TreeDiff::default());
+        }
+        let expected = if golden.is_dir() {
+            without_empty_dirs(tree::read(golden)?)
+        } else {
+            Tree::new()
+        };
+        Ok(TreeDiff::new(&expected, &actual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn blessing_rewrites_golden_directory() -> color_eyre::Result<()> {
+        let golden = TestFiles::new();
+        golden.file("stale/old.txt", "old").file("a.txt", "old");
+        let files = TestFiles::new();
+        files.file("a.txt", "new").file("b/c.txt", "added").dir("d");
+
+        assert!(files
+            .snapshot(Path::new(""), golden.path(), true)?
+            .is_empty());
+        assert!(files
+            .snapshot(Path::new(""), golden.path(), false)?
+            .is_empty());
+        assert!(!golden.path().join("stale").exists());
+        assert!(!golden.path().join("d").exists());
+        assert_eq!(fs::read_to_string(golden.path().join("b/c.txt"))?, "added");
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_empty_directories() -> color_eyre::Result<()> {
+        let golden = TestFiles::new();
+        golden.file("a.txt", "ok").dir("stale");
+        let files = TestFiles::new();
+        files.file("a.txt", "ok").dir("cache");
+
+        assert!(files
+            .snapshot(Path::new(""), golden.path(), false)?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! Handles on directories nested inside the fixture tree.
+use crate::{normalize, Operation, Result, TestFiles, TestFilesError};
+use std::path::{Path, PathBuf};
+
+/// A directory inside a [`TestFiles`], resolving paths relative to
+/// itself rather than to the temporary root.  See [`TestFiles::sub`].
+#[derive(Clone, Debug)]
+pub struct SubDir<'a> {
+    files: &'a TestFiles,
+    prefix: PathBuf,
+    path: PathBuf,
+}
+
+impl<'a> SubDir<'a> {
+    /// Creates a directory, and any missing parents, under this
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.sub("home").dir(".cache");
+    ///
+    /// assert!(temp_dir.path().join("home").join(".cache").is_dir());
+    /// ```
+    pub fn dir(&self, path: impl AsRef<Path>) -> &Self {
+        self.try_dir(path).unwrap()
+    }
+
+    /// Creates a plain file under this directory, with specified
+    /// content.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.sub("workspace").file("a/b.txt", "ok");
+    ///
+    /// let file_path = temp_dir.path().join("workspace").join("a").join("b.txt");
+    /// assert_eq!(fs::read_to_string(file_path).unwrap(), "ok");
+    /// ```
+    pub fn file(&self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> &Self {
+        self.try_file(path, content).unwrap()
+    }
+
+    /// Returns the path of this directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let cache = temp_dir.sub("cache");
+    ///
+    /// assert_eq!(cache.path(), temp_dir.path().join("cache"));
+    /// assert!(cache.path().is_dir());
+    /// ```
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn relative(&self, path: &Path, operation: Operation) -> Result<PathBuf> {
+        let normalized = normalize(path).ok_or_else(|| TestFilesError::PathEscapesRoot {
+            operation,
+            path: path.to_owned(),
+            absolute: self.path.join(path),
+        })?;
+        Ok(self.prefix.join(normalized))
+    }
+
+    /// Returns a handle on a directory nested in this one, creating
+    /// it if necessary.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.sub("home").sub(".config").file("app.toml", "");
+    ///
+    /// assert!(temp_dir.path().join("home/.config/app.toml").is_file());
+    /// ```
+    pub fn sub(&self, path: impl AsRef<Path>) -> SubDir<'a> {
+        self.try_sub(path).unwrap()
+    }
+
+    /// Tries to create a directory, and any missing parents, under
+    /// this directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_sub("home")?.try_dir(".cache")?;
+    ///
+    /// assert!(temp_dir.path().join("home").join(".cache").is_dir());
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_dir(&self, path: impl AsRef<Path>) -> Result<&Self> {
+        self.files
+            .try_dir(self.relative(path.as_ref(), Operation::CreateDir)?)?;
+        Ok(self)
+    }
+
+    /// Tries to create a plain file under this directory, with
+    /// specified content.  Paths may not escape this directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let workspace = temp_dir.try_sub("workspace")?;
+    /// workspace.try_file("a/b.txt", "ok")?;
+    ///
+    /// assert!(workspace.try_file("../escaped.txt", "no").is_err());
+    /// let file_path = temp_dir.path().join("workspace").join("a").join("b.txt");
+    /// assert_eq!(fs::read_to_string(file_path).unwrap(), "ok");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_file(&self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<&Self> {
+        self.files
+            .try_file(self.relative(path.as_ref(), Operation::Write)?, content)?;
+        Ok(self)
+    }
+
+    /// Tries to return a handle on a directory nested in this one,
+    /// creating it if necessary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let config = temp_dir.try_sub("home")?.try_sub(".config")?;
+    ///
+    /// assert_eq!(config.path(), temp_dir.path().join("home").join(".config"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_sub(&self, path: impl AsRef<Path>) -> Result<SubDir<'a>> {
+        self.files
+            .try_sub(self.relative(path.as_ref(), Operation::CreateDir)?)
+    }
+}
+
+impl TestFiles {
+    /// Returns a handle on the directory `path`, relative to the
+    /// temporary directory, creating it if necessary.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// fn populate_home(home: &test_files::SubDir) {
+    ///     home.file(".profile", "export EDITOR=vi\n");
+    /// }
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// populate_home(&temp_dir.sub("home"));
+    ///
+    /// let file_path = temp_dir.path().join("home").join(".profile");
+    /// assert_eq!(fs::read_to_string(file_path).unwrap(), "export EDITOR=vi\n");
+    /// ```
+    pub fn sub(&self, path: impl AsRef<Path>) -> SubDir<'_> {
+        self.try_sub(path).unwrap()
+    }
+
+    /// Tries to return a handle on the directory `path`, relative to
+    /// the temporary directory, creating it if necessary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let workspace = temp_dir.try_sub("workspace")?;
+    /// workspace.try_file("Cargo.toml", "[workspace]")?;
+    ///
+    /// assert!(temp_dir.path().join("workspace").join("Cargo.toml").is_file());
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_sub(&self, path: impl AsRef<Path>) -> Result<SubDir<'_>> {
+        let path = path.as_ref();
+        let prefix = normalize(path).ok_or_else(|| TestFilesError::PathEscapesRoot {
+            operation: Operation::CreateDir,
+            path: path.to_owned(),
+            absolute: self.path().join(path),
+        })?;
+        self.try_dir(&prefix)?;
+        Ok(SubDir {
+            files: self,
+            path: self.path().join(&prefix),
+            prefix,
+        })
+    }
+}
@@ -0,0 +1,153 @@
+//! Writing fixture files from templates with `{{name}}` placeholders.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+
+/// Expands every `{{name}}` placeholder of `template` from `vars`,
+/// and every `{{{{` to a literal `{{`, returning the name of the
+/// first placeholder without a value.
+fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[start..].starts_with("{{{{") {
+            rendered.push_str(&rest[..start + 2]);
+            rest = &rest[start + 4..];
+            continue;
+        }
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        let value = vars.get(name).ok_or_else(|| name.to_owned())?;
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(value);
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+impl TestFiles {
+    /// Creates a file under temporary directory from `template`,
+    /// expanding its placeholders, see [`TestFiles::try_template`].
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.template("config.toml", "name = \"{{name}}\"", [("name", "demo")]);
+    ///
+    /// assert_eq!(temp_dir.read("config.toml"), "name = \"demo\"");
+    /// ```
+    pub fn template<K, V>(
+        &self,
+        path: impl AsRef<Path>,
+        template: &str,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> &Self
+    where
+        K: AsRef<str>,
+        V: Display,
+    {
+        self.try_template(path, template, vars).unwrap()
+    }
+
+    /// Tries to create a file under temporary directory from
+    /// `template`, replacing each `{{name}}` placeholder with the
+    /// value of `name` in `vars`.  `{{root}}` expands to the path of
+    /// the temporary directory unless `vars` overrides it, and a
+    /// placeholder without a value is an error.  Write `{{{{` for a
+    /// literal `{{`, e.g. in GitHub Actions, Helm or Jinja files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use test_files::Operation;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// let mut vars = BTreeMap::new();
+    /// vars.insert("db", "data/app.db");
+    /// temp_dir.try_template("app.toml", "db = \"{{ root }}/{{ db }}\"", &vars)?;
+    ///
+    /// let expected = format!("db = \"{}/data/app.db\"", temp_dir.path().display());
+    /// assert_eq!(temp_dir.try_read("app.toml")?, expected);
+    ///
+    /// temp_dir.try_template("ci.yml", "sha: ${{{{ github.sha }}", &vars)?;
+    /// assert_eq!(temp_dir.try_read("ci.yml")?, "sha: ${{ github.sha }}");
+    ///
+    /// let error = temp_dir
+    ///     .try_template("other.toml", "{{unknown}}", &vars)
+    ///     .unwrap_err();
+    /// assert_eq!(error.operation(), Operation::Render);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_template<K, V>(
+        &self,
+        path: impl AsRef<Path>,
+        template: &str,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<&Self>
+    where
+        K: AsRef<str>,
+        V: Display,
+    {
+        let path = path.as_ref();
+        let mut values = BTreeMap::new();
+        values.insert("root".to_owned(), self.path().display().to_string());
+        values.extend(
+            vars.into_iter()
+                .map(|(name, value)| (name.as_ref().to_owned(), value.to_string())),
+        );
+        let content = render(template, &values)
+            .map_err(|name| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no value for template variable `{}`", name),
+                )
+            })
+            .map_err(TestFilesError::io(
+                Operation::Render,
+                path,
+                self.path().join(path),
+            ))?;
+        self.try_file(path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders() {
+        let mut vars = BTreeMap::new();
+        vars.insert("a".to_owned(), "1".to_owned());
+        vars.insert("b".to_owned(), "{{a}}".to_owned());
+
+        assert_eq!(
+            render("{{a}}-{{ b }}-{{a", &vars),
+            Ok("1-{{a}}-{{a".to_owned())
+        );
+        assert_eq!(render("x {{c}} y", &vars), Err("c".to_owned()));
+    }
+
+    #[test]
+    fn renders_escaped_braces_literally() {
+        let mut vars = BTreeMap::new();
+        vars.insert("a".to_owned(), "1".to_owned());
+
+        assert_eq!(
+            render("${{{{ c }} {{{{{{a}}", &vars),
+            Ok("${{ c }} {{1".to_owned())
+        );
+    }
+}
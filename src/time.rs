@@ -0,0 +1,206 @@
+//! Deterministic access and modification times for fixture entries.
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use filetime::FileTime;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Returns the time `duration` before now, for use with
+/// [`TestFiles::mtime`] and friends.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// let two_hours_ago = test_files::ago(Duration::from_secs(2 * 60 * 60));
+/// assert!(two_hours_ago < SystemTime::now());
+/// ```
+pub fn ago(duration: Duration) -> SystemTime {
+    SystemTime::now() - duration
+}
+
+impl TestFiles {
+    /// Sets the access time of `path`, relative to the temporary
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a.txt", "ok").atime("a.txt", time);
+    ///
+    /// let accessed = fs::metadata(temp_dir.path().join("a.txt")).unwrap().accessed().unwrap();
+    /// assert_eq!(accessed, time);
+    /// ```
+    pub fn atime(&self, path: impl AsRef<Path>, time: SystemTime) -> &Self {
+        self.try_atime(path, time).unwrap()
+    }
+
+    /// Sets the modification time of `path`, relative to the
+    /// temporary directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::Duration;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir
+    ///     .file("old.txt", "ok")
+    ///     .mtime("old.txt", test_files::ago(Duration::from_secs(2 * 60 * 60)))
+    ///     .file("new.txt", "ok");
+    ///
+    /// let modified = |name| fs::metadata(temp_dir.path().join(name)).unwrap().modified().unwrap();
+    /// assert!(modified("old.txt") < modified("new.txt"));
+    /// ```
+    pub fn mtime(&self, path: impl AsRef<Path>, time: SystemTime) -> &Self {
+        self.try_mtime(path, time).unwrap()
+    }
+
+    /// Sets both access and modification times of every entry under
+    /// the temporary directory, including directories and the root
+    /// itself, to `time`.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::UNIX_EPOCH;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("a/b.txt", "ok").stamp(UNIX_EPOCH);
+    ///
+    /// let modified = fs::metadata(temp_dir.path().join("a")).unwrap().modified().unwrap();
+    /// assert_eq!(modified, UNIX_EPOCH);
+    /// ```
+    pub fn stamp(&self, time: SystemTime) -> &Self {
+        self.try_stamp(time).unwrap()
+    }
+
+    /// Tries to set the access time of `path`, relative to the
+    /// temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::UNIX_EPOCH;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_atime("a.txt", UNIX_EPOCH)?;
+    ///
+    /// let accessed = fs::metadata(temp_dir.path().join("a.txt")).unwrap().accessed().unwrap();
+    /// assert_eq!(accessed, UNIX_EPOCH);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_atime(&self, path: impl AsRef<Path>, time: SystemTime) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::SetTimes)?;
+        filetime::set_file_atime(&absolute, FileTime::from_system_time(time))
+            .map_err(TestFilesError::io(Operation::SetTimes, path, &absolute))?;
+        Ok(self)
+    }
+
+    /// Tries to set the modification time of `path`, relative to the
+    /// temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_mtime("a.txt", time)?;
+    ///
+    /// let modified = fs::metadata(temp_dir.path().join("a.txt")).unwrap().modified().unwrap();
+    /// assert_eq!(modified, time);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_mtime(&self, path: impl AsRef<Path>, time: SystemTime) -> Result<&Self> {
+        let path = path.as_ref();
+        let absolute = self.slash(path, Operation::SetTimes)?;
+        filetime::set_file_mtime(&absolute, FileTime::from_system_time(time))
+            .map_err(TestFilesError::io(Operation::SetTimes, path, &absolute))?;
+        Ok(self)
+    }
+
+    /// Tries to set both access and modification times of every
+    /// entry under the temporary directory, including directories
+    /// and the root itself, to `time`.  Symlinks are stamped rather
+    /// than their targets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    /// use std::time::UNIX_EPOCH;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok")?.try_file("b.txt", "ok")?.try_stamp(UNIX_EPOCH)?;
+    ///
+    /// let modified = |name| fs::metadata(temp_dir.path().join(name)).unwrap().modified().unwrap();
+    /// assert_eq!(modified("a.txt"), modified("b.txt"));
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_stamp(&self, time: SystemTime) -> Result<&Self> {
+        stamp(self.path(), Path::new(""), FileTime::from_system_time(time))?;
+        Ok(self)
+    }
+
+    /// Stamps a freshly written file with the epoch configured through
+    /// [`Builder::epoch`](crate::Builder::epoch), if any.
+    pub(crate) fn stamp_epoch(&self, path: &Path, absolute: &Path) -> Result<()> {
+        if let Some(epoch) = self.epoch {
+            let time = FileTime::from_system_time(epoch);
+            filetime::set_file_times(absolute, time, time).map_err(TestFilesError::io(
+                Operation::SetTimes,
+                path,
+                absolute,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+fn stamp(root: &Path, relative: &Path, time: FileTime) -> Result<()> {
+    let absolute = root.join(relative);
+    let metadata = fs::symlink_metadata(&absolute).map_err(TestFilesError::io(
+        Operation::SetTimes,
+        relative,
+        &absolute,
+    ))?;
+    if metadata.is_dir() {
+        let read_dir_error = || TestFilesError::io(Operation::ReadDir, relative, &absolute);
+        for entry in fs::read_dir(&absolute).map_err(read_dir_error())? {
+            let entry = entry.map_err(read_dir_error())?;
+            stamp(root, &relative.join(entry.file_name()), time)?;
+        }
+    }
+    // Directories are stamped last, as stamping their entries may
+    // update their access time.
+    filetime::set_symlink_file_times(&absolute, time, time).map_err(TestFilesError::io(
+        Operation::SetTimes,
+        relative,
+        &absolute,
+    ))
+}
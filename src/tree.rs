@@ -0,0 +1,197 @@
+//! Reading a directory back into memory, for exporting and comparing
+//! fixture trees.
+use crate::{create_parent, Operation, Result, TestFilesError};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A single entry of a [`Tree`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Entry {
+    /// A directory, only recorded when empty.
+    Dir,
+    File(Vec<u8>),
+    /// A file which is not valid UTF-8, known only by its length, as
+    /// listed in a txtar archive.
+    Binary(usize),
+    /// A text file which could not be embedded in a txtar archive,
+    /// known only by its length.
+    Text(usize),
+    Symlink(PathBuf),
+}
+
+impl Entry {
+    /// Describes the entry in a line, for when a full diff is not
+    /// appropriate.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Dir => "directory".to_string(),
+            Self::File(content) => format!("file ({} bytes)", content.len()),
+            Self::Binary(len) => format!("binary file ({} bytes)", len),
+            Self::Text(len) => format!("text file ({} bytes)", len),
+            Self::Symlink(target) => format!("symlink to {}", target.display()),
+        }
+    }
+
+    /// Returns whether an `actual` entry read from disk matches this
+    /// expected one, where a [`Entry::Binary`] or [`Entry::Text`]
+    /// matches any binary or text file of its length.
+    pub(crate) fn matches(&self, actual: &Entry) -> bool {
+        match (self, actual) {
+            (Self::Binary(len), Self::File(content)) => {
+                content.len() == *len && std::str::from_utf8(content).is_err()
+            }
+            (Self::Text(len), Self::File(content)) => {
+                content.len() == *len && std::str::from_utf8(content).is_ok()
+            }
+            (expected, actual) => expected == actual,
+        }
+    }
+}
+
+/// Every file, symlink and empty directory under a directory, keyed
+/// by path relative to that directory.  Symlinks are recorded rather
+/// than followed.
+pub(crate) type Tree = BTreeMap<PathBuf, Entry>;
+
+/// Reads every file, symlink and empty directory under `root` into a
+/// [`Tree`].  Errors carry paths relative to `root`.
+pub(crate) fn read(root: &Path) -> Result<Tree> {
+    let mut tree = Tree::new();
+    read_into(root, Path::new(""), &mut tree)?;
+    Ok(tree)
+}
+
+fn read_into(root: &Path, relative: &Path, tree: &mut Tree) -> Result<()> {
+    let absolute = root.join(relative);
+    let read_dir_error = || TestFilesError::io(Operation::ReadDir, relative, &absolute);
+    let mut empty = true;
+    for entry in fs::read_dir(&absolute).map_err(read_dir_error())? {
+        let entry = entry.map_err(read_dir_error())?;
+        empty = false;
+        let path = relative.join(entry.file_name());
+        let file_type = entry.file_type().map_err(read_dir_error())?;
+        if file_type.is_dir() {
+            read_into(root, &path, tree)?;
+        } else if file_type.is_symlink() {
+            let target = fs::read_link(entry.path()).map_err(TestFilesError::io(
+                Operation::Read,
+                &path,
+                entry.path(),
+            ))?;
+            tree.insert(path, Entry::Symlink(target));
+        } else {
+            let content = fs::read(entry.path()).map_err(TestFilesError::io(
+                Operation::Read,
+                &path,
+                entry.path(),
+            ))?;
+            tree.insert(path, Entry::File(content));
+        }
+    }
+    if empty && relative != Path::new("") {
+        tree.insert(relative.to_owned(), Entry::Dir);
+    }
+    Ok(())
+}
+
+/// Formats a relative path with `/` separators regardless of platform.
+pub(crate) fn display(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Makes the entries under `root` match `tree`, removing any which
+/// are not part of it.
+pub(crate) fn write(root: &Path, tree: &Tree) -> Result<()> {
+    let existing = if root.is_dir() {
+        read(root)?
+    } else {
+        Tree::new()
+    };
+    for (path, entry) in &existing {
+        if tree.get(path) != Some(entry) {
+            let absolute = root.join(path);
+            let removed = match entry {
+                Entry::Dir => fs::remove_dir(&absolute),
+                _ => fs::remove_file(&absolute),
+            };
+            removed.map_err(TestFilesError::io(Operation::Remove, path, &absolute))?;
+            // Don't leave behind directories emptied by the removal,
+            // which would be read back as empty directory entries.
+            for parent in path.ancestors().skip(1) {
+                if parent == Path::new("") || fs::remove_dir(root.join(parent)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    for (path, entry) in tree {
+        if existing.get(path) == Some(entry) {
+            continue;
+        }
+        let absolute = root.join(path);
+        match entry {
+            Entry::Dir => fs::create_dir_all(&absolute).map_err(TestFilesError::io(
+                Operation::CreateDir,
+                path,
+                &absolute,
+            ))?,
+            Entry::File(content) => {
+                create_parent(path, &absolute)?;
+                fs::write(&absolute, content).map_err(TestFilesError::io(
+                    Operation::Write,
+                    path,
+                    &absolute,
+                ))?
+            }
+            Entry::Binary(_) | Entry::Text(_) => {
+                return Err(TestFilesError::io(Operation::Write, path, &absolute)(
+                    io::Error::new(io::ErrorKind::InvalidData, "file content is unknown"),
+                ))
+            }
+            Entry::Symlink(target) => {
+                create_parent(path, &absolute)?;
+                symlink(target, &absolute).map_err(TestFilesError::io(
+                    Operation::Symlink,
+                    path,
+                    &absolute,
+                ))?
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Creates a symlink at `link` pointing to `target`, which is not
+/// required to exist.
+#[cfg(unix)]
+pub(crate) fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Creates a symlink at `link` pointing to `target`.  Windows needs
+/// to know whether the target is a directory, so dangling links are
+/// created as file links.
+#[cfg(windows)]
+pub(crate) fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link
+        .parent()
+        .map_or_else(|| target.to_owned(), |parent| parent.join(target));
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are not supported on this platform",
+    ))
+}
@@ -0,0 +1,421 @@
+//! Support for the [txtar](https://pkg.go.dev/golang.org/x/tools/txtar)
+//! archive format: an optional comment followed by files, each one
+//! introduced by a `-- path --` header line.  As an extension, a header
+//! with a trailing `/` and no content, `-- path/ --`, denotes an empty
+//! directory, and content which cannot be embedded is replaced by a
+//! single placeholder line, `[symlink to TARGET]`,
+//! `[binary file, N bytes]` or `[text file, N bytes]`.
+use crate::tree::{self, Entry, Tree};
+use crate::{Operation, Result, TestFiles, TestFilesError};
+use std::io;
+
+/// Splits a txtar archive into `(path, content)` sections, discarding
+/// the leading comment.  Like the Go implementation, a final newline
+/// is added to any non-empty content missing one.
+pub(crate) fn parse(archive: &str) -> Vec<(&str, String)> {
+    let mut files = Vec::new();
+    let mut current: Option<(&str, String)> = None;
+    for line in archive.split_inclusive('\n') {
+        if let Some(name) = marker(line) {
+            files.extend(current.take());
+            current = Some((name, String::new()));
+        } else if let Some((_, content)) = current.as_mut() {
+            content.push_str(line);
+        }
+    }
+    if let Some((name, mut content)) = current {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        files.push((name, content));
+    }
+    files
+}
+
+/// Parses a txtar archive into a [`Tree`], recognising the
+/// placeholders written by [`format`].  Directories are dropped unless
+/// empty, matching [`tree::read`].
+pub(crate) fn to_tree(archive: &str) -> Tree {
+    let mut tree: Tree = parse(archive)
+        .into_iter()
+        .map(|(path, content)| {
+            if let Some(path) = directory(path) {
+                (path.into(), Entry::Dir)
+            } else if let Some(target) = symlink_target(&content) {
+                (path.into(), Entry::Symlink(target.into()))
+            } else if let Some(len) = binary_len(&content) {
+                (path.into(), Entry::Binary(len))
+            } else if let Some(len) = text_len(&content) {
+                (path.into(), Entry::Text(len))
+            } else {
+                (path.into(), Entry::File(content.into_bytes()))
+            }
+        })
+        .collect();
+    let parents: Vec<_> = tree
+        .keys()
+        .flat_map(|path| path.ancestors().skip(1))
+        .map(|path| path.to_owned())
+        .collect();
+    for parent in parents {
+        if tree.get(&parent) == Some(&Entry::Dir) {
+            tree.remove(&parent);
+        }
+    }
+    tree
+}
+
+/// Returns the directory path of a section name with a trailing `/`.
+fn directory(name: &str) -> Option<&str> {
+    name.strip_suffix('/')
+}
+
+/// Adds the final newline a txtar archive would give to non-empty text
+/// content missing one.
+pub(crate) fn fix_newline(mut content: Vec<u8>) -> Vec<u8> {
+    if std::str::from_utf8(&content).is_ok() && !content.is_empty() && !content.ends_with(b"\n") {
+        content.push(b'\n');
+    }
+    content
+}
+
+/// Returns the target of a `[symlink to TARGET]` placeholder.
+fn symlink_target(content: &str) -> Option<&str> {
+    content
+        .strip_suffix('\n')?
+        .strip_prefix("[symlink to ")?
+        .strip_suffix(']')
+}
+
+/// Returns the length of a `[binary file, N bytes]` placeholder.
+fn binary_len(content: &str) -> Option<usize> {
+    content
+        .strip_suffix('\n')?
+        .strip_prefix("[binary file, ")?
+        .strip_suffix(" bytes]")?
+        .parse()
+        .ok()
+}
+
+/// Returns the length of a `[text file, N bytes]` placeholder.
+fn text_len(content: &str) -> Option<usize> {
+    content
+        .strip_suffix('\n')?
+        .strip_prefix("[text file, ")?
+        .strip_suffix(" bytes]")?
+        .parse()
+        .ok()
+}
+
+/// Returns whether `text` reads back unchanged when embedded in an
+/// archive: it must end with a newline, contain no header lines and
+/// not be mistaken for a placeholder.
+fn embeddable(text: &str) -> bool {
+    text.is_empty()
+        || text.ends_with('\n')
+            && !text
+                .split_inclusive('\n')
+                .any(|line| marker(line).is_some())
+            && symlink_target(text).is_none()
+            && binary_len(text).is_none()
+            && text_len(text).is_none()
+}
+
+fn marker(line: &str) -> Option<&str> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let name = line.strip_prefix("-- ")?.strip_suffix(" --")?.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Formats a [`Tree`] as a txtar archive.  Content which is not valid
+/// UTF-8, or text which would not read back unchanged, is flagged
+/// with its length rather than embedded, and symlinks are flagged
+/// with their target.
+pub(crate) fn format(tree: &Tree) -> String {
+    let mut archive = String::new();
+    for (path, entry) in tree {
+        match entry {
+            Entry::Dir => archive.push_str(&format!("-- {}/ --\n", tree::display(path))),
+            _ => archive.push_str(&format!("-- {} --\n", tree::display(path))),
+        }
+        match entry {
+            Entry::Dir => {}
+            Entry::File(content) => match std::str::from_utf8(content) {
+                Ok(text) if embeddable(text) => archive.push_str(text),
+                Ok(_) => archive.push_str(&format!("[text file, {} bytes]\n", content.len())),
+                Err(_) => archive.push_str(&format!("[binary file, {} bytes]\n", content.len())),
+            },
+            Entry::Binary(len) => archive.push_str(&format!("[binary file, {} bytes]\n", len)),
+            Entry::Text(len) => archive.push_str(&format!("[text file, {} bytes]\n", len)),
+            Entry::Symlink(target) => {
+                archive.push_str(&format!("[symlink to {}]\n", target.display()))
+            }
+        }
+    }
+    archive
+}
+
+impl TestFiles {
+    /// Creates a new temporary directory populated with the files
+    /// of a txtar archive.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use indoc::indoc;
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::from_txtar(indoc! {"
+    ///     -- a/b/c.txt --
+    ///     ok
+    ///     -- b/c/d.txt --
+    ///     fine
+    /// "});
+    ///
+    /// let file_path = temp_dir.path().join("a").join("b").join("c.txt");
+    /// let written_content = fs::read_to_string(file_path).unwrap();
+    /// assert_eq!(written_content, "ok\n");
+    /// ```
+    pub fn from_txtar(archive: &str) -> Self {
+        Self::try_from_txtar(archive).unwrap()
+    }
+
+    /// Returns every file under the temporary directory as a txtar
+    /// archive, in sorted path order.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.file("b.txt", "no newline").file("a/c.txt", "ok\n");
+    ///
+    /// assert_eq!(
+    ///     temp_dir.to_txtar(),
+    ///     "-- a/c.txt --\nok\n-- b.txt --\n[text file, 10 bytes]\n"
+    /// );
+    /// ```
+    pub fn to_txtar(&self) -> String {
+        self.try_to_txtar().unwrap()
+    }
+
+    /// Tries to create a new temporary directory populated with
+    /// the files of a txtar archive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let temp_dir = test_files::TestFiles::try_from_txtar("-- a.txt --\nok\n");
+    ///
+    /// assert!(temp_dir.is_ok());
+    /// assert!(temp_dir.unwrap().path().join("a.txt").is_file());
+    /// ```
+    pub fn try_from_txtar(archive: &str) -> Result<Self> {
+        let files = Self::try_new()?;
+        files.try_txtar(archive)?;
+        Ok(files)
+    }
+
+    /// Tries to return every file under the temporary directory as
+    /// a txtar archive, in sorted path order.  Paths are relative to
+    /// the temporary root and use `/` separators; content that is
+    /// not valid UTF-8 is replaced by a `[binary file, N bytes]` line
+    /// and symlinks by a `[symlink to TARGET]` line.  Empty directories
+    /// are listed with a trailing `/`.
+    ///
+    /// Text is embedded only if it would load back unchanged: it must
+    /// end with a newline and contain no `-- name --` header lines or
+    /// lone placeholder lines.  Other text is replaced by a
+    /// `[text file, N bytes]` line.  Only text files embedded in full,
+    /// symlinks and empty directories load back with
+    /// [`TestFiles::try_txtar`]; any archive compares equal to its own
+    /// tree with [`TestFiles::diff_tree`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_file("a.txt", "ok\n")?.try_symlink("b", "a.txt")?;
+    ///
+    /// let archive = temp_dir.try_to_txtar()?;
+    /// assert_eq!(archive, "-- a.txt --\nok\n-- b --\n[symlink to a.txt]\n");
+    /// assert_eq!(test_files::TestFiles::from_txtar(&archive).to_txtar(), archive);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_to_txtar(&self) -> Result<String> {
+        Ok(format(&self.collapse_root(tree::read(self.path())?)))
+    }
+
+    /// Tries to create every file of a txtar archive under the
+    /// temporary directory.  Any comment preceding the first file
+    /// header is ignored, headers with a trailing `/` create (empty)
+    /// directories and `[symlink to TARGET]` placeholders create
+    /// symlinks.  `[binary file, N bytes]` and `[text file, N bytes]`
+    /// placeholders are an error, as their content is unknown.  Any
+    /// [`Builder::root_token`](crate::Builder::root_token) in the
+    /// content is replaced with the path of the temporary directory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use indoc::indoc;
+    /// use std::fs;
+    ///
+    /// # fn main() -> test_files::Result<()> {
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.try_txtar(indoc! {"
+    ///     This comment is ignored.
+    ///     -- a/b/c.txt --
+    ///     ok
+    ///     -- b/c/d.txt --
+    ///     fine
+    /// "})?;
+    ///
+    /// let file_path = temp_dir.path().join("b").join("c").join("d.txt");
+    /// let written_content = fs::read_to_string(file_path).unwrap();
+    /// assert_eq!(written_content, "fine\n");
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn try_txtar(&self, archive: &str) -> Result<&Self> {
+        for (path, content) in parse(archive) {
+            if let Some(path) = directory(path) {
+                self.try_dir(path)?;
+            } else if let Some(target) = symlink_target(&content) {
+                self.try_symlink(path, target)?;
+            } else if binary_len(&content).is_some() || text_len(&content).is_some() {
+                return Err(TestFilesError::io(
+                    Operation::Write,
+                    path,
+                    self.path().join(path),
+                )(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "file content is not recorded in the archive",
+                )));
+            } else {
+                self.try_file(path, self.expand_root(content.into_bytes()))?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Creates every file of a txtar archive under the temporary
+    /// directory.
+    ///
+    /// Panics on failure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs;
+    ///
+    /// let temp_dir = test_files::TestFiles::new();
+    /// temp_dir.txtar("-- a.txt --\nok\n");
+    ///
+    /// let written_content = fs::read_to_string(temp_dir.path().join("a.txt")).unwrap();
+    /// assert_eq!(written_content, "ok\n");
+    /// ```
+    pub fn txtar(&self, archive: &str) -> &Self {
+        self.try_txtar(archive).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::path::Path;
+
+    #[test]
+    fn parses_sections() {
+        let archive = indoc! {"
+            comment
+            -- a.txt --
+            one
+            two
+            --  b.txt  --
+            -- not a marker
+            -- c.txt --
+            last"};
+
+        assert_eq!(
+            parse(archive),
+            vec![
+                ("a.txt", "one\ntwo\n".to_string()),
+                ("b.txt", "-- not a marker\n".to_string()),
+                ("c.txt", "last\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_sections() {
+        let mut tree = Tree::new();
+        tree.insert("a/b.txt".into(), Entry::File(b"no newline".to_vec()));
+        tree.insert("c.bin".into(), Entry::File(vec![0xff, 0xfe]));
+        tree.insert("d".into(), Entry::Symlink("a/b.txt".into()));
+        tree.insert("e".into(), Entry::Dir);
+        tree.insert("empty".into(), Entry::File(Vec::new()));
+        tree.insert("f.txt".into(), Entry::File(b"one\n-- g --\n".to_vec()));
+        tree.insert("h.txt".into(), Entry::File(b"[symlink to x]\n".to_vec()));
+        tree.insert("i.txt".into(), Entry::File(b"ok\n".to_vec()));
+
+        assert_eq!(
+            format(&tree),
+            indoc! {"
+                -- a/b.txt --
+                [text file, 10 bytes]
+                -- c.bin --
+                [binary file, 2 bytes]
+                -- d --
+                [symlink to a/b.txt]
+                -- e/ --
+                -- empty --
+                -- f.txt --
+                [text file, 12 bytes]
+                -- h.txt --
+                [text file, 15 bytes]
+                -- i.txt --
+                ok
+            "}
+        );
+    }
+
+    #[test]
+    fn keeps_only_empty_directories() {
+        let tree = to_tree("-- a/ --\n-- a/b/ --\n-- c/ --\n-- c/d.txt --\n");
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get(Path::new("a/b")), Some(&Entry::Dir));
+        assert_eq!(
+            tree.get(Path::new("c/d.txt")),
+            Some(&Entry::File(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn recognises_placeholders() {
+        assert_eq!(symlink_target("[symlink to ../a b]\n"), Some("../a b"));
+        assert_eq!(symlink_target("[symlink to a]\nmore\n"), None);
+        assert_eq!(binary_len("[binary file, 12 bytes]\n"), Some(12));
+        assert_eq!(binary_len("[binary file, many bytes]\n"), None);
+        assert_eq!(text_len("[text file, 3 bytes]\n"), Some(3));
+    }
+
+    #[test]
+    fn keeps_empty_sections_empty() {
+        assert_eq!(
+            parse("-- a.txt --\n-- b.txt --"),
+            vec![("a.txt", String::new()), ("b.txt", String::new())]
+        );
+    }
+}